@@ -0,0 +1,72 @@
+//! Замеры хеширования блоков и проверки цепочки.
+//!
+//! Запуск: `cargo bench`.
+
+use criterion::{BenchmarkId, Criterion, black_box, criterion_group, criterion_main};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rustblockchain::{
+    Block, Blockchain, ChainBuilder, MAX_TRANSACTIONS_PER_BLOCK, Transaction, merkle_root,
+};
+
+/// Случайный перевод между случайными адресами со случайной подписью.
+///
+/// Адреса почти наверняка не повторяются, поэтому nonce у каждой
+/// транзакции нулевой. Первый байт отправителя ненулевой, чтобы транзакция
+/// не оказалась coinbase.
+fn random_transaction(rng: &mut StdRng) -> Transaction {
+    let mut from: [u8; 32] = rng.r#gen();
+    from[0] |= 1;
+    let mut tx = Transaction::new(from, rng.r#gen(), rng.gen_range(1..1_000_000))
+        .with_fee(rng.gen_range(0..100));
+    rng.fill(&mut tx.signature[..]);
+    tx
+}
+
+fn random_transactions(rng: &mut StdRng, count: usize) -> Vec<Transaction> {
+    (0..count).map(|_| random_transaction(rng)).collect()
+}
+
+fn random_chain(blocks: usize) -> Blockchain {
+    let mut rng = StdRng::seed_from_u64(blocks as u64);
+    let mut builder = ChainBuilder::new();
+    for _ in 0..blocks {
+        let count = rng.gen_range(1..=MAX_TRANSACTIONS_PER_BLOCK);
+        builder = builder.block(random_transactions(&mut rng, count));
+    }
+    builder.build()
+}
+
+fn bench_block_hash(c: &mut Criterion) {
+    let mut rng = StdRng::seed_from_u64(1);
+    let mut group = c.benchmark_group("block_hash");
+    for count in [1, 10, 100, 1000] {
+        let block = Block::new(1, 1, random_transactions(&mut rng, count), [1; 32]);
+        group.bench_with_input(
+            BenchmarkId::new("calculate_hash", count),
+            &block,
+            |b, block| b.iter(|| black_box(block).calculate_hash()),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("merkle_root", count),
+            &block.transactions,
+            |b, transactions| b.iter(|| merkle_root(black_box(transactions))),
+        );
+    }
+    group.finish();
+}
+
+fn bench_is_valid(c: &mut Criterion) {
+    let mut group = c.benchmark_group("is_valid");
+    for blocks in [10, 100, 1000] {
+        let chain = random_chain(blocks);
+        assert!(chain.is_valid());
+        group.bench_with_input(BenchmarkId::from_parameter(blocks), &chain, |b, chain| {
+            b.iter(|| black_box(chain).is_valid())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_block_hash, bench_is_valid);
+criterion_main!(benches);
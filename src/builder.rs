@@ -0,0 +1,100 @@
+//! Построитель цепочек для тестов.
+
+use crate::chain::Blockchain;
+use crate::core::{ChainError, NANOS_PER_SEC, Transaction};
+
+/// Построитель цепочки с детерминированными timestamp.
+///
+/// Цепочка начинается с генезиса `Blockchain::new_deterministic`, а каждый
+/// следующий блок получает timestamp на секунду больше предыдущего, поэтому
+/// одинаковые последовательности вызовов дают одинаковые хеши.
+///
+/// ```
+/// use rustblockchain::ChainBuilder;
+///
+/// let chain = ChainBuilder::new()
+///     .transfer([1; 32], [2; 32], 10)
+///     .transfer([1; 32], [3; 32], 5)
+///     .build();
+/// assert_eq!(chain.len(), 3);
+/// assert!(chain.is_valid());
+/// ```
+#[derive(Debug)]
+pub struct ChainBuilder {
+    chain: Blockchain,
+}
+
+impl ChainBuilder {
+    /// Построитель с детерминированным генезис-блоком.
+    pub fn new() -> Self {
+        Self {
+            chain: Blockchain::new_deterministic(),
+        }
+    }
+
+    /// Добавляет блок с заданными транзакциями.
+    ///
+    /// Паникует, если транзакции не проходят проверку (см. `try_add_block`).
+    pub fn block(mut self, transactions: Vec<Transaction>) -> Self {
+        let now = self.chain.tip().map_or(0, |tip| tip.timestamp) + NANOS_PER_SEC;
+        if let Err(e) = self.chain.append_block(transactions, 0, now) {
+            panic!("{}", e);
+        }
+        self
+    }
+
+    /// Добавляет блок с одним переводом; nonce отправителя подбирается
+    /// автоматически.
+    ///
+    /// Паникует, если nonce отправителя исчерпаны (см. `next_nonce`).
+    pub fn transfer(self, from: [u8; 32], to: [u8; 32], amount: u64) -> Self {
+        let Some(nonce) = self.chain.next_nonce(&from) else {
+            panic!("{}", ChainError::NonceExhausted { addr: from });
+        };
+        self.block(vec![Transaction::new(from, to, amount).with_nonce(nonce)])
+    }
+
+    /// Готовая цепочка.
+    pub fn build(self) -> Blockchain {
+        self.chain
+    }
+}
+
+impl Default for ChainBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder_creates_valid_chain() {
+        let chain = ChainBuilder::new()
+            .transfer([1; 32], [2; 32], 10)
+            .block(vec![
+                Transaction::new([3; 32], [4; 32], 1),
+                Transaction::new([5; 32], [4; 32], 2),
+            ])
+            .transfer([1; 32], [4; 32], 3)
+            .build();
+        assert_eq!(chain.len(), 4);
+        assert!(chain.is_valid());
+        assert_eq!(chain.blocks[3].transactions[0].nonce, 1);
+        assert_eq!(chain.blocks[2].timestamp, 2 * NANOS_PER_SEC);
+    }
+
+    #[test]
+    fn test_builder_is_deterministic() {
+        let build = || ChainBuilder::new().transfer([1; 32], [2; 32], 10).build();
+        assert_eq!(build(), build());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_builder_panics_on_invalid_transactions() {
+        ChainBuilder::new().transfer([1; 32], [2; 32], 0);
+    }
+}
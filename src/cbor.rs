@@ -0,0 +1,143 @@
+//! Сериализация в CBOR (feature `cbor`) для обмена с системами, не
+//! понимающими bincode.
+//!
+//! Через serde массивы `[u8; N]` (хеши, адреса, подписи) и `Vec<u8>` попадают
+//! в CBOR как массивы чисел. Перед записью такие непустые массивы заменяются
+//! байтовыми строками, а при чтении байтовые строки превращаются обратно
+//! в массивы, поэтому хеш занимает 34 байта, а не 32–64.
+//!
+//! Структуры записываются как отображения с именами полей, поэтому CBOR
+//! примерно на треть больше bincode: цепочка из 100 блоков с одной
+//! подписанной транзакцией занимает около 44 КБ против 33 КБ.
+
+use crate::chain::Blockchain;
+use crate::core::{Block, ChainError};
+use ciborium::value::{Integer, Value};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+/// Сериализация цепочки в CBOR.
+pub fn serialize_blockchain_cbor(chain: &Blockchain) -> Result<Vec<u8>, ChainError> {
+    encode(chain)
+}
+
+/// Десериализация цепочки из CBOR (без проверки целостности).
+pub fn deserialize_blockchain_cbor(bytes: &[u8]) -> Result<Blockchain, ChainError> {
+    let mut chain: Blockchain = decode(bytes)?;
+    chain.invalidate_caches();
+    Ok(chain)
+}
+
+/// Сериализация блока в CBOR.
+pub fn serialize_block_cbor(block: &Block) -> Result<Vec<u8>, ChainError> {
+    encode(block)
+}
+
+/// Десериализация блока из CBOR.
+pub fn deserialize_block_cbor(bytes: &[u8]) -> Result<Block, ChainError> {
+    decode(bytes)
+}
+
+fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, ChainError> {
+    let mut value = Value::serialized(value).map_err(cbor_error)?;
+    arrays_to_bytes(&mut value);
+    let mut bytes = Vec::new();
+    ciborium::into_writer(&value, &mut bytes).map_err(cbor_error)?;
+    Ok(bytes)
+}
+
+fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, ChainError> {
+    let mut value: Value = ciborium::from_reader(bytes).map_err(cbor_error)?;
+    bytes_to_arrays(&mut value);
+    value.deserialized().map_err(cbor_error)
+}
+
+fn cbor_error(e: impl std::fmt::Display) -> ChainError {
+    ChainError::Cbor(e.to_string())
+}
+
+/// Заменяет непустые массивы чисел от 0 до 255 байтовыми строками.
+fn arrays_to_bytes(value: &mut Value) {
+    match value {
+        Value::Array(items) => {
+            let bytes: Option<Vec<u8>> = items
+                .iter()
+                .map(|item| item.as_integer().and_then(|i| u8::try_from(i).ok()))
+                .collect();
+            match bytes {
+                Some(bytes) if !bytes.is_empty() => *value = Value::Bytes(bytes),
+                _ => items.iter_mut().for_each(arrays_to_bytes),
+            }
+        }
+        Value::Map(entries) => entries.iter_mut().for_each(|(_, v)| arrays_to_bytes(v)),
+        Value::Tag(_, inner) => arrays_to_bytes(inner),
+        _ => {}
+    }
+}
+
+/// Обратное к `arrays_to_bytes`: байтовые строки становятся массивами чисел.
+fn bytes_to_arrays(value: &mut Value) {
+    match value {
+        Value::Bytes(bytes) => {
+            let items = bytes
+                .iter()
+                .map(|&byte| Value::Integer(Integer::from(byte)))
+                .collect();
+            *value = Value::Array(items);
+        }
+        Value::Array(items) => items.iter_mut().for_each(bytes_to_arrays),
+        Value::Map(entries) => entries.iter_mut().for_each(|(_, v)| bytes_to_arrays(v)),
+        Value::Tag(_, inner) => bytes_to_arrays(inner),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SigningKey;
+    use crate::chain::serialize_blockchain;
+    use crate::core::Transaction;
+
+    #[test]
+    fn test_cbor_roundtrip() {
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let mut chain = Blockchain::new_named("cbor");
+        for i in 0..100 {
+            let mut tx =
+                Transaction::new(key.verifying_key().to_bytes(), [2; 32], 10).with_nonce(i);
+            tx.sign(&key);
+            chain.add_block(vec![tx]);
+        }
+        let bytes = serialize_blockchain_cbor(&chain).unwrap();
+        let restored = deserialize_blockchain_cbor(&bytes).unwrap();
+        assert_eq!(restored, chain);
+        assert!(restored.is_valid());
+        assert_eq!(restored.name(), Some("cbor"));
+        assert_eq!(
+            restored.get_block_by_hash(&chain.blocks[50].hash),
+            Some(&chain.blocks[50])
+        );
+
+        let tip = chain.tip().unwrap();
+        let block = deserialize_block_cbor(&serialize_block_cbor(tip).unwrap()).unwrap();
+        assert_eq!(&block, tip);
+
+        let hash = Value::Bytes(tip.hash.to_vec());
+        let mut encoded_hash = Vec::new();
+        ciborium::into_writer(&hash, &mut encoded_hash).unwrap();
+        assert!(bytes.windows(34).any(|window| window == encoded_hash));
+
+        let bincode_len = serialize_blockchain(&chain).unwrap().len();
+        assert!(
+            bytes.len() < bincode_len * 3 / 2,
+            "{} > {}",
+            bytes.len(),
+            bincode_len
+        );
+        assert!(matches!(
+            deserialize_blockchain_cbor(&bytes[..bytes.len() / 2]),
+            Err(ChainError::Cbor(_))
+        ));
+    }
+}
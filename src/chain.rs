@@ -0,0 +1,6171 @@
+//! Цепочка блоков и всё, что требует `std`: системное время, файлы,
+//! сериализация через `bincode`, консенсус.
+//!
+//! Под `wasm32` файловые API (`save_to_file`, `load_from_file`, `BlockLog`)
+//! не собираются, а `SystemClock` с feature `wasm` берёт время из
+//! JavaScript, а не из `SystemTime`.
+
+use crate::core::{
+    Block, BlockHeader, ChainError, Hasher, MAX_DIFFICULTY, MAX_TRANSACTIONS_PER_BLOCK, MultiSig,
+    NANOS_PER_SEC, Sha256Hasher, Transaction, address_filter, body_root, check_genesis,
+    create_block, create_genesis_block, create_genesis_block_with_allocation,
+    create_genesis_block_with_metadata, leading_zero_bits, legacy_body_root, merkle_root,
+    total_amount, total_fees,
+};
+use crate::network::OrphanPool;
+use bincode::Options;
+#[cfg(feature = "compression")]
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use log::{debug, trace};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_big_array::BigArray;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs::{File, OpenOptions};
+#[cfg(not(target_arch = "wasm32"))]
+use std::io::{BufRead, BufReader, BufWriter};
+use std::io::{Read, Write};
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+#[cfg(not(all(target_arch = "wasm32", feature = "wasm")))]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Функция возвращает текущее время в наносекундах с Unix-эпохи.
+///
+/// `u64` наносекунд хватает примерно до 2554 года; после этого функция
+/// возвращает `u64::MAX`, а не молча обрезает значение, и следующий блок
+/// не создаётся с `ChainError::TimestampOverflow` (см. `create_block`).
+#[cfg(not(all(target_arch = "wasm32", feature = "wasm")))]
+fn current_timestamp() -> u64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Системное время установлено до Unix-эпохи")
+        .as_nanos();
+    u64::try_from(nanos).unwrap_or(u64::MAX)
+}
+
+/// Текущее время в браузере: `SystemTime::now` под `wasm32` паникует,
+/// поэтому время берётся из `Date.now()` (с точностью до миллисекунды).
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+fn current_timestamp() -> u64 {
+    (js_sys::Date::now() as u64).saturating_mul(1_000_000)
+}
+
+/// Источник времени, которым цепочка проставляет timestamp новых блоков.
+pub trait Clock: fmt::Debug + Send + Sync {
+    /// Текущее время в наносекундах с Unix-эпохи.
+    fn now_nanos(&self) -> u64;
+}
+
+/// Дополнительные правила сети для отдельных транзакций (предельная сумма,
+/// разрешённые отправители и т. п.).
+///
+/// Вызывается `try_add_block` и другими методами добавления блока для каждой
+/// транзакции, кроме coinbase, после встроенных проверок подписи, суммы и
+/// повторов. Для отказа подходит `ChainError::TransactionRejected`.
+pub trait TxValidator: fmt::Debug + Send + Sync {
+    /// Проверка транзакции `tx`, предлагаемой для добавления в `chain`.
+    fn validate(&self, tx: &Transaction, chain: &Blockchain) -> Result<(), ChainError>;
+}
+
+/// Проверка по умолчанию: принимает любую транзакцию.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NoopTxValidator;
+
+impl TxValidator for NoopTxValidator {
+    fn validate(&self, _tx: &Transaction, _chain: &Blockchain) -> Result<(), ChainError> {
+        Ok(())
+    }
+}
+
+/// Системные часы (по умолчанию).
+///
+/// Под `wasm32` с feature `wasm` показывают время JavaScript (`Date.now()`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_nanos(&self) -> u64 {
+        current_timestamp()
+    }
+}
+
+/// Часы для тестов: показывают заданное время, пока его не изменят.
+///
+/// Разделяются через `Arc`, поэтому время можно двигать после передачи
+/// часов в `ChainConfig::clock`.
+#[derive(Debug, Default)]
+pub struct MockClock {
+    now: AtomicU64,
+}
+
+impl MockClock {
+    /// Часы, показывающие `now` нс.
+    pub fn new(now: u64) -> Self {
+        Self {
+            now: AtomicU64::new(now),
+        }
+    }
+
+    /// Устанавливает время `now` нс.
+    pub fn set(&self, now: u64) {
+        self.now.store(now, Ordering::SeqCst);
+    }
+
+    /// Сдвигает время вперёд на `nanos` нс (не дальше `u64::MAX`).
+    pub fn advance(&self, nanos: u64) {
+        let _ = self
+            .now
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |now| {
+                Some(now.saturating_add(nanos))
+            });
+    }
+}
+
+impl Clock for MockClock {
+    fn now_nanos(&self) -> u64 {
+        self.now.load(Ordering::SeqCst)
+    }
+}
+
+/// Количество последних интервалов между блоками, по которым
+/// `Blockchain::estimated_hashrate` оценивает хешрейт.
+pub const HASHRATE_WINDOW: usize = 10;
+
+/// Параметры перерасчёта сложности майнинга.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MiningParams {
+    /// Количество последних интервалов между блоками, по которым оценивается скорость.
+    pub window: usize,
+    /// Целевой интервал между блоками в секундах (timestamp блоков — в наносекундах).
+    pub target_block_secs: u64,
+    /// Сложность первых блоков, пока в цепочке недостаточно истории.
+    pub initial_difficulty: u32,
+}
+
+impl Default for MiningParams {
+    fn default() -> Self {
+        Self {
+            window: 5,
+            target_block_secs: 10,
+            initial_difficulty: 8,
+        }
+    }
+}
+
+/// Функция вычисления сложности блока, следующего за `blocks`.
+///
+/// Если последние `window` блоков появились более чем вдвое быстрее целевого
+/// интервала, сложность увеличивается на 1, если более чем вдвое медленнее — уменьшается на 1.
+fn expected_difficulty(blocks: &[Block], params: &MiningParams) -> u32 {
+    if params.window == 0 || blocks.len() <= params.window {
+        return params.initial_difficulty;
+    }
+    let last = &blocks[blocks.len() - 1];
+    let first = &blocks[blocks.len() - 1 - params.window];
+    let elapsed = last.timestamp.saturating_sub(first.timestamp) as u128;
+    let expected = params.window as u128 * params.target_block_secs as u128 * NANOS_PER_SEC as u128;
+    if elapsed < expected / 2 {
+        last.difficulty.saturating_add(1).min(MAX_DIFFICULTY)
+    } else if elapsed > expected * 2 {
+        last.difficulty.saturating_sub(1)
+    } else {
+        last.difficulty
+    }
+}
+
+/// Сводная статистика цепочки.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainStats {
+    /// Количество блоков (включая генезис).
+    pub block_count: usize,
+    /// Количество транзакций во всех блоках, кроме генезиса.
+    pub transaction_count: usize,
+    /// Суммарный объём этих транзакций.
+    pub total_amount: u128,
+    /// Средний размер блока в байтах после сериализации bincode.
+    pub average_block_size_bytes: usize,
+    /// Хеш последнего блока (`None` для пустой цепочки).
+    pub last_hash: Option<[u8; 32]>,
+    /// Результат проверки целостности цепочки.
+    pub is_valid: bool,
+}
+
+/// Балансы адресов и последние nonce отправителей после блока с индексом
+/// `index`.
+///
+/// Снимок обновляется инкрементально методом `apply_block` по мере
+/// поступления новых блоков, без пересчёта от генезиса.
+///
+/// Балансы хранятся в `i128`: суммы и комиссии — это `u64`, поэтому при
+/// воспроизведении цепочки баланс не переполняется, пока транзакций меньше
+/// 2^62.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateSnapshot {
+    /// Балансы адресов (правила — как у `Blockchain::balances`).
+    pub balances: HashMap<[u8; 32], i128>,
+    /// Индекс последнего учтённого блока.
+    pub index: u64,
+    /// Последние использованные nonce отправителей (кроме coinbase).
+    pub nonces: HashMap<[u8; 32], u64>,
+}
+
+impl StateSnapshot {
+    /// Применяет транзакции блока к балансам и nonce.
+    ///
+    /// Блоки должны применяться по порядку: снимок не проверяет, что
+    /// `block.index` следует за `index`.
+    ///
+    /// Если баланс какого-либо адреса выходит за пределы `i128`, возвращается
+    /// `ChainError::BalanceOverflow`, а снимок не меняется.
+    pub fn apply_block(&mut self, block: &Block) -> Result<(), ChainError> {
+        let mut updated: HashMap<[u8; 32], i128> = HashMap::new();
+        let mut change = |addr: [u8; 32], delta: i128| -> Result<(), ChainError> {
+            let balance = updated
+                .get(&addr)
+                .or_else(|| self.balances.get(&addr))
+                .copied()
+                .unwrap_or(0);
+            let balance = balance
+                .checked_add(delta)
+                .ok_or(ChainError::BalanceOverflow {
+                    index: block.index,
+                    addr,
+                })?;
+            updated.insert(addr, balance);
+            Ok(())
+        };
+        for tx in &block.transactions {
+            if !tx.is_coinbase() {
+                change(tx.from, -(tx.amount as i128 + tx.fee as i128))?;
+            }
+            change(tx.to, tx.amount as i128)?;
+        }
+        self.balances.extend(updated);
+        for tx in block.transactions.iter().filter(|tx| !tx.is_coinbase()) {
+            self.nonces.insert(tx.from, tx.nonce);
+        }
+        self.index = block.index;
+        Ok(())
+    }
+}
+
+/// Настройки сети, по которым цепочка принимает и проверяет блоки.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChainConfig {
+    /// Максимальное количество транзакций в одном блоке.
+    pub max_transactions_per_block: usize,
+    /// Параметры перерасчёта сложности. Если заданы, валидация требует,
+    /// чтобы каждый блок имел сложность, вычисленную по этим параметрам.
+    pub mining: Option<MiningParams>,
+    /// Запрет уводить баланс отправителя в минус при добавлении блока.
+    pub overdraft_protection: bool,
+    /// Требование действительных подписей у всех транзакций.
+    pub verify_signatures: bool,
+    /// Минимальный интервал между блоками в наносекундах.
+    pub min_block_interval: Option<u64>,
+    /// Максимальный интервал между блоками в наносекундах.
+    pub max_block_interval: Option<u64>,
+    /// Допускать блоки с timestamp, равным timestamp предыдущего блока.
+    pub allow_equal_timestamps: bool,
+    /// Хеш-функция блоков. Не сохраняется вместе с цепочкой: после загрузки
+    /// используется SHA-256. Цепочки с разными хеш-функциями несовместимы.
+    #[serde(skip, default = "default_hasher")]
+    pub hasher: Arc<dyn Hasher>,
+    /// Часы, по которым новые блоки получают timestamp. Не сохраняются
+    /// вместе с цепочкой: после загрузки используются системные часы.
+    #[serde(skip, default = "default_clock")]
+    pub clock: Arc<dyn Clock>,
+    /// Правила сети для транзакций новых блоков. Не сохраняются вместе
+    /// с цепочкой: после загрузки используется `NoopTxValidator`.
+    #[serde(skip, default = "default_tx_validator")]
+    pub tx_validator: Arc<dyn TxValidator>,
+    /// Награда за блок: сумма coinbase-транзакции, которую блок после
+    /// генезиса может содержать первой транзакцией. 0 — награды нет.
+    pub block_reward: u64,
+    /// Адрес, на который `add_block` начисляет награду за новые блоки.
+    pub miner: Option<[u8; 32]>,
+    /// Сложность генезис-блока. Генезис не участвует в перерасчёте
+    /// сложности (`mining`): его сложность задаётся только этим полем.
+    pub genesis_difficulty: u32,
+    /// Максимальный размер нового блока в байтах после сериализации bincode.
+    pub max_block_bytes: Option<usize>,
+    /// Контрольные точки: пары «индекс блока → ожидаемый хеш». Цепочка,
+    /// блок которой на такой высоте имеет другой хеш, отклоняется.
+    pub checkpoints: Vec<(u64, [u8; 32])>,
+}
+
+impl ChainConfig {
+    /// Проверка подписей транзакций блока, если она включена
+    /// (coinbase-транзакции не подписываются).
+    fn check_signatures(&self, block: &Block) -> Result<(), ChainError> {
+        if let Some(position) = block
+            .transactions
+            .iter()
+            .position(|tx| self.requires_signature(tx) && !tx.verify())
+        {
+            return Err(ChainError::InvalidSignature {
+                index: block.index,
+                position,
+            });
+        }
+        Ok(())
+    }
+
+    /// Проверяется ли подпись транзакции: мультиподпись проверяется всегда,
+    /// обычная подпись — при `verify_signatures`, coinbase — никогда.
+    fn requires_signature(&self, tx: &Transaction) -> bool {
+        !tx.is_coinbase() && (self.verify_signatures || tx.multisig.is_some())
+    }
+
+    /// Проверка, что блок на высоте контрольной точки имеет ожидаемый хеш.
+    fn check_checkpoint(&self, index: u64, hash: &[u8; 32]) -> Result<(), ChainError> {
+        match self.checkpoints.iter().find(|(height, _)| *height == index) {
+            Some((_, expected)) if expected != hash => Err(ChainError::CheckpointMismatch {
+                index,
+                expected: *expected,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Сумма coinbase-транзакции для блока с транзакциями `transactions`:
+    /// награда за блок плюс их комиссии, или `None` при переполнении `u64`.
+    fn coinbase_amount(&self, transactions: &[Transaction]) -> Option<u64> {
+        self.block_reward.checked_add(total_fees(transactions)?)
+    }
+
+    /// Проверка coinbase-транзакций блока `index > 0`: не более одной,
+    /// первой в блоке, с суммой, равной `block_reward` плюс комиссии блока.
+    fn check_coinbase(&self, index: u64, transactions: &[Transaction]) -> Result<(), ChainError> {
+        let expected = self
+            .coinbase_amount(transactions)
+            .ok_or(ChainError::AmountOverflow)?;
+        for (position, tx) in transactions.iter().enumerate() {
+            if !tx.is_coinbase() {
+                continue;
+            }
+            if position > 0 || expected == 0 {
+                return Err(ChainError::UnexpectedCoinbase { index, position });
+            }
+            if tx.amount != expected {
+                return Err(ChainError::InvalidReward {
+                    index,
+                    expected,
+                    actual: tx.amount,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+fn default_hasher() -> Arc<dyn Hasher> {
+    Arc::new(Sha256Hasher)
+}
+
+fn default_clock() -> Arc<dyn Clock> {
+    Arc::new(SystemClock)
+}
+
+fn default_tx_validator() -> Arc<dyn TxValidator> {
+    Arc::new(NoopTxValidator)
+}
+
+impl Default for ChainConfig {
+    fn default() -> Self {
+        Self {
+            max_transactions_per_block: MAX_TRANSACTIONS_PER_BLOCK,
+            mining: None,
+            overdraft_protection: false,
+            verify_signatures: false,
+            min_block_interval: None,
+            max_block_interval: None,
+            allow_equal_timestamps: false,
+            hasher: default_hasher(),
+            clock: default_clock(),
+            tx_validator: default_tx_validator(),
+            block_reward: 0,
+            miner: None,
+            genesis_difficulty: 0,
+            max_block_bytes: None,
+            checkpoints: Vec::new(),
+        }
+    }
+}
+
+/// Текущая версия формата сериализованной цепочки.
+///
+/// Более старые форматы читает `Blockchain::migrate`:
+/// - версия 1 — без поля `version`;
+/// - версия 2 — без `ChainConfig::genesis_difficulty`;
+/// - версия 3 — без `ChainConfig::max_block_bytes`;
+/// - версия 4 — без `Transaction::multisig`;
+/// - версия 5 — без `Block::metadata`;
+/// - версия 6 — без `Block::address_filter`;
+/// - версия 7 — без `ChainConfig::checkpoints`;
+/// - версия 8 — без снимка состояния удалённых блоков (см. `prune_before`);
+/// - версия 9 — хеши могли вычисляться с целыми числами в little-endian
+///   (переход на big-endian не менял номер версии).
+pub const CHAIN_FORMAT_VERSION: u16 = 10;
+
+/// Структура блокчейна.
+///
+/// Клон цепочки независим от оригинала, но не получает его обработчики
+/// `on_block_added`.
+///
+/// При десериализации граница удалённой части (`pruned_before`) проверяется
+/// по самим блокам (см. `StoredChain`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "StoredChain")]
+pub struct Blockchain {
+    /// Версия формата, в котором цепочка сериализуется (`CHAIN_FORMAT_VERSION`).
+    version: u16,
+    pub blocks: Vec<Block>,
+    /// Настройки сети.
+    pub config: ChainConfig,
+    /// Индекс «хеш блока → позиция в `blocks`» для поиска за O(1).
+    ///
+    /// Стоит около 40 байт на блок; не сериализуется и может устареть при прямом
+    /// изменении `blocks`, поэтому поиск перепроверяет найденный блок.
+    #[serde(skip)]
+    hash_index: HashMap<[u8; 32], usize>,
+    /// Длина префикса цепочки, уже проверенного `validate_incremental`
+    /// или `add_block_validated`.
+    #[serde(skip)]
+    last_validated_len: usize,
+    /// Индекс, до которого у блоков удалены транзакции (см. `prune_before`).
+    pruned_before: u64,
+    /// Балансы и nonce после последнего блока с удалёнными транзакциями
+    /// (при `pruned_before == 0` — пустой снимок).
+    pruned_state: StateSnapshot,
+    /// Обработчики, вызываемые после добавления блока (см. `on_block_added`).
+    /// Не копируются при клонировании.
+    #[serde(skip)]
+    observers: BlockObservers,
+    /// Заголовки, проверенные `import_headers` и ожидающие тел блоков.
+    #[serde(skip)]
+    pending_headers: Vec<BlockHeader>,
+    /// Балансы после последнего блока (см. `balances_cached`). Вычисляются
+    /// при первом обращении и затем обновляются по мере добавления блоков;
+    /// сбрасываются при удалении блоков и в `invalidate_caches`.
+    #[serde(skip)]
+    balance_cache: OnceLock<StateSnapshot>,
+}
+
+/// Сериализованные поля `Blockchain` текущей версии формата.
+///
+/// Цепочка читается через эту структуру, чтобы не доверять записанной
+/// границе удалённой части: она принимается, только если у всех блоков ниже
+/// неё нет транзакций, а снимок состояния относится к последнему из них.
+#[derive(Deserialize)]
+struct StoredChain {
+    version: u16,
+    blocks: Vec<Block>,
+    config: ChainConfig,
+    pruned_before: u64,
+    pruned_state: StateSnapshot,
+}
+
+impl From<StoredChain> for Blockchain {
+    fn from(stored: StoredChain) -> Self {
+        let mut chain = Self::from_blocks_unchecked(stored.blocks);
+        chain.version = stored.version;
+        chain.config = stored.config;
+        chain.restore_pruned(stored.pruned_before, stored.pruned_state);
+        chain
+    }
+}
+
+/// Цепочка в формате версии 1: без `version` и с настройками версии 2.
+#[derive(Serialize, Deserialize)]
+struct BlockchainV1 {
+    blocks: Vec<BlockV4>,
+    config: ChainConfigV2,
+    pruned_before: u64,
+}
+
+/// Цепочка в формате версии 2 и новее: с полем `version`, настройками `C`
+/// и блоками `B`.
+#[derive(Serialize, Deserialize)]
+struct VersionedChain<C, B> {
+    version: u16,
+    blocks: Vec<B>,
+    config: C,
+    pruned_before: u64,
+}
+
+/// Блок в форматах версий 1–4: с транзакциями без мультиподписи.
+#[derive(Serialize, Deserialize)]
+struct BlockV4 {
+    index: u64,
+    timestamp: u64,
+    transactions: Vec<TransactionV4>,
+    merkle_root: [u8; 32],
+    previous_hash: [u8; 32],
+    nonce: u64,
+    difficulty: u32,
+    hash: [u8; 32],
+}
+
+/// Блок в формате версии 5: без `metadata`.
+#[derive(Serialize, Deserialize)]
+struct BlockV5 {
+    index: u64,
+    timestamp: u64,
+    transactions: Vec<Transaction>,
+    merkle_root: [u8; 32],
+    previous_hash: [u8; 32],
+    nonce: u64,
+    difficulty: u32,
+    hash: [u8; 32],
+}
+
+/// Блок в формате версии 6: без `address_filter`.
+#[derive(Serialize, Deserialize)]
+struct BlockV6 {
+    index: u64,
+    timestamp: u64,
+    transactions: Vec<Transaction>,
+    merkle_root: [u8; 32],
+    previous_hash: [u8; 32],
+    nonce: u64,
+    difficulty: u32,
+    hash: [u8; 32],
+    metadata: Vec<u8>,
+}
+
+/// Транзакция в форматах версий 1–4: без `multisig`.
+#[derive(Serialize, Deserialize)]
+struct TransactionV4 {
+    from: [u8; 32],
+    to: [u8; 32],
+    amount: u64,
+    nonce: u64,
+    fee: u64,
+    #[serde(with = "BigArray")]
+    signature: [u8; 64],
+}
+
+impl From<BlockV4> for Block {
+    fn from(block: BlockV4) -> Self {
+        BlockV5 {
+            index: block.index,
+            timestamp: block.timestamp,
+            transactions: block.transactions.into_iter().map(Into::into).collect(),
+            merkle_root: block.merkle_root,
+            previous_hash: block.previous_hash,
+            nonce: block.nonce,
+            difficulty: block.difficulty,
+            hash: block.hash,
+        }
+        .into()
+    }
+}
+
+impl From<BlockV5> for Block {
+    fn from(block: BlockV5) -> Self {
+        BlockV6 {
+            index: block.index,
+            timestamp: block.timestamp,
+            transactions: block.transactions,
+            merkle_root: block.merkle_root,
+            previous_hash: block.previous_hash,
+            nonce: block.nonce,
+            difficulty: block.difficulty,
+            hash: block.hash,
+            metadata: Vec::new(),
+        }
+        .into()
+    }
+}
+
+/// Фильтр адресов строится по сохранённым транзакциям; у блоков с удалёнными
+/// телами он пуст, как и их список транзакций.
+impl From<BlockV6> for Block {
+    fn from(block: BlockV6) -> Self {
+        Self {
+            index: block.index,
+            timestamp: block.timestamp,
+            address_filter: address_filter(&block.transactions),
+            transactions: block.transactions,
+            merkle_root: block.merkle_root,
+            previous_hash: block.previous_hash,
+            nonce: block.nonce,
+            difficulty: block.difficulty,
+            hash: block.hash,
+            metadata: block.metadata,
+        }
+    }
+}
+
+impl From<TransactionV4> for Transaction {
+    fn from(tx: TransactionV4) -> Self {
+        let mut converted = Transaction::new(tx.from, tx.to, tx.amount)
+            .with_nonce(tx.nonce)
+            .with_fee(tx.fee);
+        converted.signature = tx.signature;
+        converted
+    }
+}
+
+/// Настройки сети в форматах версий 1 и 2.
+#[derive(Serialize, Deserialize)]
+struct ChainConfigV2 {
+    max_transactions_per_block: usize,
+    mining: Option<MiningParams>,
+    overdraft_protection: bool,
+    verify_signatures: bool,
+    min_block_interval: Option<u64>,
+    max_block_interval: Option<u64>,
+    allow_equal_timestamps: bool,
+    block_reward: u64,
+    miner: Option<[u8; 32]>,
+}
+
+/// Настройки сети в формате версии 3.
+#[derive(Serialize, Deserialize)]
+struct ChainConfigV3 {
+    max_transactions_per_block: usize,
+    mining: Option<MiningParams>,
+    overdraft_protection: bool,
+    verify_signatures: bool,
+    min_block_interval: Option<u64>,
+    max_block_interval: Option<u64>,
+    allow_equal_timestamps: bool,
+    block_reward: u64,
+    miner: Option<[u8; 32]>,
+    genesis_difficulty: u32,
+}
+
+/// Настройки сети в форматах версий 4–7: без `checkpoints`.
+#[derive(Serialize, Deserialize)]
+struct ChainConfigV7 {
+    max_transactions_per_block: usize,
+    mining: Option<MiningParams>,
+    overdraft_protection: bool,
+    verify_signatures: bool,
+    min_block_interval: Option<u64>,
+    max_block_interval: Option<u64>,
+    allow_equal_timestamps: bool,
+    block_reward: u64,
+    miner: Option<[u8; 32]>,
+    genesis_difficulty: u32,
+    max_block_bytes: Option<usize>,
+}
+
+impl From<ChainConfigV7> for ChainConfig {
+    fn from(config: ChainConfigV7) -> Self {
+        Self {
+            max_transactions_per_block: config.max_transactions_per_block,
+            mining: config.mining,
+            overdraft_protection: config.overdraft_protection,
+            verify_signatures: config.verify_signatures,
+            min_block_interval: config.min_block_interval,
+            max_block_interval: config.max_block_interval,
+            allow_equal_timestamps: config.allow_equal_timestamps,
+            block_reward: config.block_reward,
+            miner: config.miner,
+            genesis_difficulty: config.genesis_difficulty,
+            max_block_bytes: config.max_block_bytes,
+            ..Self::default()
+        }
+    }
+}
+
+impl From<ChainConfigV3> for ChainConfig {
+    fn from(config: ChainConfigV3) -> Self {
+        Self {
+            max_transactions_per_block: config.max_transactions_per_block,
+            mining: config.mining,
+            overdraft_protection: config.overdraft_protection,
+            verify_signatures: config.verify_signatures,
+            min_block_interval: config.min_block_interval,
+            max_block_interval: config.max_block_interval,
+            allow_equal_timestamps: config.allow_equal_timestamps,
+            block_reward: config.block_reward,
+            miner: config.miner,
+            genesis_difficulty: config.genesis_difficulty,
+            ..Self::default()
+        }
+    }
+}
+
+impl From<ChainConfigV2> for ChainConfig {
+    fn from(config: ChainConfigV2) -> Self {
+        Self {
+            max_transactions_per_block: config.max_transactions_per_block,
+            mining: config.mining,
+            overdraft_protection: config.overdraft_protection,
+            verify_signatures: config.verify_signatures,
+            min_block_interval: config.min_block_interval,
+            max_block_interval: config.max_block_interval,
+            allow_equal_timestamps: config.allow_equal_timestamps,
+            block_reward: config.block_reward,
+            miner: config.miner,
+            ..Self::default()
+        }
+    }
+}
+
+/// Обработчик добавления блока (см. `Blockchain::on_block_added`).
+///
+/// Должен быть `Send + Sync`, чтобы цепочку можно было передавать между
+/// потоками (см. `SharedBlockchain`).
+pub type BlockCallback = Box<dyn FnMut(&Block) + Send + Sync>;
+
+/// Список обработчиков добавления блока.
+#[derive(Default)]
+struct BlockObservers(Vec<BlockCallback>);
+
+/// Обработчики не клонируются: клон начинает с пустого списка.
+impl Clone for BlockObservers {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl fmt::Debug for BlockObservers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "BlockObservers({})", self.0.len())
+    }
+}
+
+impl Default for Blockchain {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Все блоки цепочки в формате `Display` для `Block`, каждый с пустой
+/// строкой после него (как в `print_chain`).
+impl fmt::Display for Blockchain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for block in &self.blocks {
+            writeln!(f, "{}", block)?;
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Цепочки равны, если совпадают их блоки.
+///
+/// Настройки узла и служебные индексы (`hash_index`, граница проверенной
+/// части) в сравнении не участвуют: они не сериализуются или зависят от узла,
+/// поэтому цепочка равна своей копии после сериализации.
+impl PartialEq for Blockchain {
+    fn eq(&self, other: &Self) -> bool {
+        self.blocks == other.blocks
+    }
+}
+
+impl Eq for Blockchain {}
+
+impl<'a> IntoIterator for &'a Blockchain {
+    type Item = &'a Block;
+    type IntoIter = std::slice::Iter<'a, Block>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Новая цепочка (`Blockchain::new`), в которую каждый пакет транзакций
+/// добавляется отдельным блоком.
+///
+/// Паникует, если пакет не проходит проверку (см. `add_block`).
+impl FromIterator<Vec<Transaction>> for Blockchain {
+    fn from_iter<I: IntoIterator<Item = Vec<Transaction>>>(batches: I) -> Self {
+        let mut chain = Self::new();
+        for transactions in batches {
+            chain.add_block(transactions);
+        }
+        chain
+    }
+}
+
+impl Blockchain {
+    /// Создание новой цепочки с добавлением генезис-блока.
+    pub fn new() -> Self {
+        Self::new_with_timestamp(SystemClock.now_nanos())
+    }
+
+    /// Создание новой цепочки, генезис-блок которой содержит имя сети `name`.
+    ///
+    /// Имя входит в генезис-хеш, поэтому сети с разными именами имеют разные
+    /// `chain_id`; прочитать его можно методом `name`.
+    pub fn new_named(name: &str) -> Self {
+        Self::from_genesis(create_genesis_block_with_metadata(
+            SystemClock.now_nanos(),
+            name.as_bytes(),
+            &Sha256Hasher,
+        ))
+    }
+
+    /// Создание новой цепочки с генезис-блоком, имеющим заданный timestamp.
+    ///
+    /// Цепочки, созданные с одинаковым timestamp, имеют одинаковый генезис-хеш.
+    pub fn new_with_timestamp(timestamp: u64) -> Self {
+        Self::from_genesis(create_genesis_block(timestamp, &Sha256Hasher))
+    }
+
+    /// Создание цепочки с начальным распределением монет.
+    ///
+    /// Каждая пара `(адрес, сумма)` зачисляется в генезис-блоке
+    /// coinbase-транзакцией, поэтому балансы доступны до первых переводов.
+    pub fn new_with_allocation(allocations: Vec<([u8; 32], u64)>) -> Self {
+        Self::from_genesis(create_genesis_block_with_allocation(
+            SystemClock.now_nanos(),
+            &allocations,
+            &Sha256Hasher,
+        ))
+    }
+
+    /// Создание цепочки с фиксированным генезис-блоком (timestamp 0).
+    ///
+    /// Генезис-хеш всегда одинаков, что позволяет писать тесты с заранее
+    /// известными хешами. Предназначено только для тестов: все такие цепочки
+    /// относятся к одной и той же «сети».
+    pub fn new_deterministic() -> Self {
+        Self::new_with_timestamp(0)
+    }
+
+    /// Создание новой цепочки с заданным генезис-блоком.
+    ///
+    /// Блок должен иметь `index == 0`, `previous_hash == [0u8; 32]` и корректный хеш.
+    /// Его сложность становится `genesis_difficulty` новой цепочки.
+    pub fn with_genesis(genesis: Block) -> Result<Self, ChainError> {
+        check_genesis(&genesis, &Sha256Hasher)?;
+        if !genesis.meets_difficulty() {
+            return Err(ChainError::InsufficientWork { index: 0 });
+        }
+        let mut chain = Self::from_genesis(genesis);
+        chain.config.genesis_difficulty = chain.blocks[0].difficulty;
+        Ok(chain)
+    }
+
+    /// Создание цепочки из одного (уже проверенного) генезис-блока.
+    fn from_genesis(genesis: Block) -> Self {
+        Blockchain {
+            version: CHAIN_FORMAT_VERSION,
+            hash_index: HashMap::from([(genesis.hash, 0)]),
+            blocks: vec![genesis],
+            config: ChainConfig::default(),
+            last_validated_len: 0,
+            pruned_before: 0,
+            pruned_state: StateSnapshot::default(),
+            observers: BlockObservers::default(),
+            pending_headers: Vec::new(),
+            balance_cache: OnceLock::new(),
+        }
+    }
+
+    /// Версия формата, в котором была сериализована цепочка.
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+
+    /// Чтение цепочки в текущем или более старом формате bincode с переводом
+    /// в текущую версию и проверкой целостности.
+    ///
+    /// Данные пробуются как текущий формат, затем как версии 9, 8, 7, 6, 5, 4,
+    /// 3, 2 и 1 (см. `CHAIN_FORMAT_VERSION`); принимается первый вариант,
+    /// прошедший проверку. По одним байтам форматы не всегда различимы, поэтому
+    /// решает именно проверка целостности.
+    ///
+    /// Старые форматы не хранят состояние удалённых блоков: у цепочки,
+    /// сокращённой `prune_before` до версии 9, балансы и nonce учитывают
+    /// только оставшиеся транзакции.
+    ///
+    /// Хеши цепочек старых версий, вычисленные по прежним правилам
+    /// (little-endian), пересчитываются, а блоки с ненулевой сложностью
+    /// заново майнятся (см. `rebuild_hashes`).
+    pub fn migrate(bytes: &[u8]) -> Result<Self, ChainError> {
+        let mut error = None;
+        let mut candidates = Vec::new();
+        match deserialize_blockchain(bytes) {
+            Ok(chain) if chain.version == CHAIN_FORMAT_VERSION => candidates.push(chain),
+            // Версия 9 отличается от текущей только правилами хеширования.
+            Ok(mut chain) if chain.version == 9 => {
+                chain.version = CHAIN_FORMAT_VERSION;
+                chain.rehash_legacy();
+                candidates.push(chain);
+            }
+            Ok(chain) if chain.version > CHAIN_FORMAT_VERSION => {
+                error = Some(ChainError::UnsupportedVersion {
+                    version: chain.version,
+                });
+            }
+            _ => {}
+        }
+        candidates.extend(Self::read_versioned::<ChainConfig, Block>(bytes, 8));
+        candidates.extend(Self::read_versioned::<ChainConfigV7, Block>(bytes, 7));
+        candidates.extend(Self::read_versioned::<ChainConfigV7, BlockV6>(bytes, 6));
+        candidates.extend(Self::read_versioned::<ChainConfigV7, BlockV5>(bytes, 5));
+        candidates.extend(Self::read_versioned::<ChainConfigV7, BlockV4>(bytes, 4));
+        candidates.extend(Self::read_versioned::<ChainConfigV3, BlockV4>(bytes, 3));
+        candidates.extend(Self::read_versioned::<ChainConfigV2, BlockV4>(bytes, 2));
+        let parse_error = match decode::<BlockchainV1>(bytes, MAX_DESERIALIZE_BYTES) {
+            Ok(legacy) => {
+                candidates.push(Self::from_legacy(
+                    1,
+                    legacy.blocks,
+                    legacy.config,
+                    legacy.pruned_before,
+                ));
+                None
+            }
+            Err(e) => Some(e),
+        };
+        for chain in candidates {
+            match chain.validate() {
+                Ok(()) => return Ok(chain),
+                Err(e) => {
+                    error.get_or_insert(e);
+                }
+            }
+        }
+        Err(error.or(parse_error).unwrap_or(ChainError::EmptyChain))
+    }
+
+    /// Чтение цепочки в формате версии `version` с настройками `C` и блоками `B`
+    /// (без проверки целостности).
+    fn read_versioned<C, B>(bytes: &[u8], version: u16) -> Option<Self>
+    where
+        C: DeserializeOwned + Into<ChainConfig>,
+        B: DeserializeOwned + Into<Block>,
+    {
+        let legacy: VersionedChain<C, B> = decode(bytes, MAX_DESERIALIZE_BYTES).ok()?;
+        (legacy.version == version)
+            .then(|| Self::from_legacy(version, legacy.blocks, legacy.config, legacy.pruned_before))
+    }
+
+    /// Цепочка текущей версии из полей формата версии `version`.
+    fn from_legacy(
+        version: u16,
+        blocks: Vec<impl Into<Block>>,
+        config: impl Into<ChainConfig>,
+        pruned_before: u64,
+    ) -> Self {
+        debug!(
+            "Цепочка из {} блоков переводится из формата версии {}",
+            blocks.len(),
+            version
+        );
+        let mut chain = Self::from_blocks_unchecked(blocks.into_iter().map(Into::into).collect());
+        chain.config = config.into();
+        let state = StateSnapshot {
+            index: pruned_before.saturating_sub(1),
+            ..StateSnapshot::default()
+        };
+        chain.restore_pruned(pruned_before, state);
+        chain.rehash_legacy();
+        chain
+    }
+
+    /// Пересчитывает хеши, вычисленные по правилам форматов до версии 10
+    /// (целые числа — little-endian, см. `BlockHeader::legacy_bytes`).
+    ///
+    /// Хеши пересчитываются, только если по прежним правилам цепочка
+    /// согласована целиком: корни Меркла, хеши, ссылки и работа. Иначе
+    /// цепочка не меняется — например, если её хеши уже вычислены
+    /// по новым правилам или данные повреждены.
+    fn rehash_legacy(&mut self) {
+        let hasher = self.config.hasher.clone();
+        let consistent = self.blocks.iter().enumerate().all(|(i, block)| {
+            (self.is_pruned(block)
+                || block.merkle_root == legacy_body_root(&block.transactions, &block.metadata))
+                && block.hash == hasher.hash(&block.header().legacy_bytes())
+                && block.meets_difficulty()
+                && (i == 0 || block.previous_hash == self.blocks[i - 1].hash)
+        });
+        if consistent {
+            debug!(
+                "Хеши цепочки пересчитываются по правилам версии {}",
+                CHAIN_FORMAT_VERSION
+            );
+            self.rebuild_hashes();
+        }
+    }
+
+    /// Принимает прочитанную границу удалённой части и снимок состояния,
+    /// если они согласуются с блоками: граница не дальше конца цепочки,
+    /// у всех блоков ниже неё нет транзакций, а снимок сделан после
+    /// последнего из них. Иначе цепочка считается несокращённой, и блоки
+    /// без транзакций проверяются целиком.
+    fn restore_pruned(&mut self, pruned_before: u64, state: StateSnapshot) {
+        let consistent = pruned_before > 0
+            && usize::try_from(pruned_before).is_ok_and(|len| {
+                len <= self.blocks.len()
+                    && self.blocks[..len]
+                        .iter()
+                        .all(|block| block.transactions.is_empty())
+            })
+            && state.index.checked_add(1) == Some(pruned_before);
+        if consistent {
+            self.pruned_before = pruned_before;
+            self.pruned_state = state;
+        } else {
+            self.pruned_before = 0;
+            self.pruned_state = StateSnapshot::default();
+        }
+    }
+
+    /// Цепочка из произвольных блоков **без какой-либо проверки**, с настройками
+    /// по умолчанию.
+    ///
+    /// Предназначена для тестов: позволяет собрать заведомо некорректную
+    /// цепочку (перед использованием её можно проверить `validate` или
+    /// согласовать хеши `rebuild_hashes`).
+    pub fn from_blocks_unchecked(blocks: Vec<Block>) -> Self {
+        let mut chain = Blockchain {
+            version: CHAIN_FORMAT_VERSION,
+            hash_index: HashMap::new(),
+            blocks,
+            config: ChainConfig::default(),
+            last_validated_len: 0,
+            pruned_before: 0,
+            pruned_state: StateSnapshot::default(),
+            observers: BlockObservers::default(),
+            pending_headers: Vec::new(),
+            balance_cache: OnceLock::new(),
+        };
+        chain.rebuild_index();
+        chain
+    }
+
+    /// Делает последовательность блоков внутренне согласованной: по порядку
+    /// проставляет индексы и ссылки на предыдущий хеш, пересчитывает корни
+    /// Меркла, фильтры адресов и хеши, а блоки с ненулевой сложностью заново майнит.
+    ///
+    /// Timestamp, транзакции и сложность не меняются, поэтому правила,
+    /// которые от них зависят (интервалы, nonce, балансы), по-прежнему могут
+    /// нарушаться. Предназначен для подготовки тестовых цепочек.
+    pub fn rebuild_hashes(&mut self) {
+        let hasher = self.config.hasher.clone();
+        let mut previous_hash = [0u8; 32];
+        for position in 0..self.blocks.len() {
+            let has_body = !self.is_pruned(&self.blocks[position]);
+            let block = &mut self.blocks[position];
+            block.index = position as u64;
+            block.previous_hash = previous_hash;
+            if has_body {
+                block.merkle_root = body_root(&block.transactions, &block.metadata);
+                block.address_filter = address_filter(&block.transactions);
+            }
+            // Блок со сложностью больше `MAX_DIFFICULTY` не намайнить: он
+            // получает обычный хеш и не пройдёт проверку работы.
+            if block.difficulty == 0
+                || block
+                    .mine_with(block.difficulty as usize, hasher.as_ref())
+                    .is_err()
+            {
+                block.hash = block.calculate_hash_with(hasher.as_ref());
+            }
+            previous_hash = block.hash;
+        }
+        self.invalidate_caches();
+    }
+
+    /// Перестраивает индекс `hash_index` по текущим блокам.
+    fn rebuild_index(&mut self) {
+        self.hash_index = self
+            .blocks
+            .iter()
+            .enumerate()
+            .map(|(position, block)| (block.hash, position))
+            .collect();
+    }
+
+    /// Включает или выключает защиту от ухода балансов в минус.
+    ///
+    /// При включённой защите `try_add_block` отклоняет блок, если после
+    /// какой-либо его транзакции баланс отправителя стал бы отрицательным.
+    pub fn with_overdraft_protection(mut self, enabled: bool) -> Self {
+        self.config.overdraft_protection = enabled;
+        self
+    }
+
+    /// Создание новой цепочки с заданными настройками сети.
+    ///
+    /// Генезис-блок получает время из `config.clock`, хешируется функцией
+    /// из `config.hasher` и майнится со сложностью `config.genesis_difficulty`.
+    ///
+    /// Паникует, если `genesis_difficulty` больше `MAX_DIFFICULTY`.
+    pub fn with_config(config: ChainConfig) -> Self {
+        let mut genesis = create_genesis_block(config.clock.now_nanos(), config.hasher.as_ref());
+        if config.genesis_difficulty > 0
+            && let Err(e) =
+                genesis.mine_with(config.genesis_difficulty as usize, config.hasher.as_ref())
+        {
+            panic!("{}", e);
+        }
+        let mut chain = Self::from_genesis(genesis);
+        chain.config = config;
+        chain
+    }
+
+    /// Создание новой цепочки с динамическим перерасчётом сложности.
+    pub fn with_mining(params: MiningParams) -> Self {
+        Self::with_config(ChainConfig {
+            mining: Some(params),
+            ..ChainConfig::default()
+        })
+    }
+
+    /// Включает или выключает проверку подписей транзакций.
+    ///
+    /// При включённой проверке `try_add_block` и `validate` отклоняют блоки,
+    /// содержащие транзакции с недействительной подписью.
+    pub fn with_signature_verification(mut self, enabled: bool) -> Self {
+        self.config.verify_signatures = enabled;
+        self
+    }
+
+    /// Задаёт часы, по которым новые блоки получают timestamp.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.config.clock = clock;
+        self
+    }
+
+    /// Включает награду за блок: `add_block` будет первой транзакцией каждого
+    /// нового блока начислять на адрес `miner` `reward` монет и комиссии
+    /// транзакций блока.
+    pub fn with_block_reward(mut self, miner: [u8; 32], reward: u64) -> Self {
+        self.config.miner = Some(miner);
+        self.config.block_reward = reward;
+        self
+    }
+
+    /// Добавляет новый блок с заданными транзакциями.
+    ///
+    /// Паникует, если транзакции не проходят проверку (см. `try_add_block`).
+    pub fn add_block(&mut self, transactions: Vec<Transaction>) {
+        self.add_block_pow(transactions, 0);
+    }
+
+    /// Добавляет новый блок, предварительно намайнив его с заданной сложностью.
+    ///
+    /// Паникует, если транзакции не проходят проверку (см. `try_add_block_pow`).
+    pub fn add_block_pow(&mut self, transactions: Vec<Transaction>, difficulty: usize) {
+        if let Err(e) = self.try_add_block_pow(transactions, difficulty) {
+            panic!("{}", e);
+        }
+    }
+
+    /// Добавляет новый блок с заданными транзакциями, возвращая ошибку
+    /// вместо паники, если транзакции не проходят проверку.
+    pub fn try_add_block(&mut self, transactions: Vec<Transaction>) -> Result<(), ChainError> {
+        self.try_add_block_pow(transactions, 0)
+    }
+
+    /// Добавляет новый намайненный блок, возвращая ошибку, если транзакции
+    /// не проходят проверку.
+    pub fn try_add_block_pow(
+        &mut self,
+        transactions: Vec<Transaction>,
+        difficulty: usize,
+    ) -> Result<(), ChainError> {
+        self.append_block(transactions, difficulty, self.config.clock.now_nanos())?;
+        self.notify_blocks_added(self.blocks.len() - 1);
+        Ok(())
+    }
+
+    /// Проверяет транзакции, создаёт (и при `difficulty > 0` майнит) блок
+    /// с временем `now` и добавляет его в конец цепочки без вызова обработчиков.
+    pub(crate) fn append_block(
+        &mut self,
+        mut transactions: Vec<Transaction>,
+        difficulty: usize,
+        now: u64,
+    ) -> Result<(), ChainError> {
+        if self.blocks.is_empty() {
+            return Err(ChainError::EmptyChain);
+        }
+        if let Some(miner) = self.config.miner
+            && let Some(amount) = self.config.coinbase_amount(&transactions)
+            && amount > 0
+        {
+            let reward = Transaction::coinbase(miner, amount).with_nonce(self.blocks.len() as u64);
+            transactions.insert(0, reward);
+        }
+        self.check_transactions(&transactions)
+            .inspect_err(|e| debug!("Транзакции для блока отклонены: {}", e))?;
+        let last_block = self.tip().ok_or(ChainError::EmptyChain)?;
+        let mut new_block = create_block(
+            transactions,
+            last_block,
+            now,
+            self.config.allow_equal_timestamps,
+            self.config.hasher.as_ref(),
+        )?;
+        self.check_block_interval(&new_block, last_block)?;
+        self.check_block_size(&new_block)?;
+        if difficulty > 0 {
+            new_block.mine_with(difficulty, self.config.hasher.as_ref())?;
+        }
+        debug!(
+            "Добавлен блок #{} ({} транзакций, хеш {})",
+            new_block.index,
+            new_block.transactions.len(),
+            hex::encode(new_block.hash)
+        );
+        self.push_block(new_block);
+        Ok(())
+    }
+
+    /// Добавляет новый блок и проверяет только его относительно предыдущего.
+    ///
+    /// Если весь предшествующий префикс уже был проверен, граница проверенной
+    /// части (`last_validated_len`) сдвигается на новый блок. При ошибке блок
+    /// не добавляется.
+    pub fn add_block_validated(
+        &mut self,
+        transactions: Vec<Transaction>,
+    ) -> Result<(), ChainError> {
+        self.append_block(transactions, 0, self.config.clock.now_nanos())?;
+        self.check_last_block()?;
+        self.notify_blocks_added(self.blocks.len() - 1);
+        Ok(())
+    }
+
+    /// Регистрирует обработчик, вызываемый после добавления каждого нового
+    /// блока (`add_block`, `try_add_block`, `add_block_validated` и т. п.).
+    ///
+    /// Обработчики вызываются по порядку регистрации, когда блок уже находится
+    /// в цепочке. Обработчик получает только блок и не должен пытаться
+    /// изменять цепочку: во время вызова она заимствована изменяемо.
+    pub fn on_block_added(&mut self, callback: BlockCallback) {
+        self.observers.0.push(callback);
+    }
+
+    /// Вызов обработчиков для блоков, начиная с позиции `from`.
+    fn notify_blocks_added(&mut self, from: usize) {
+        for block in &self.blocks[from..] {
+            for callback in &mut self.observers.0 {
+                callback(block);
+            }
+        }
+    }
+
+    /// Присоединяет к вершине блоки-сироты из `pool`, чьи родители уже
+    /// в цепочке, каскадно: присоединённый блок может оказаться родителем
+    /// следующих сирот. Возвращает число присоединённых блоков.
+    ///
+    /// Сироты, не прошедшие проверку, и соперники присоединённого блока
+    /// удаляются из пула.
+    pub fn connect_orphans(&mut self, pool: &mut OrphanPool) -> usize {
+        let mut connected = 0;
+        while let Some(tip) = self.tip().map(|block| block.hash) {
+            let children = pool.take_children(&tip);
+            if children.is_empty() {
+                break;
+            }
+            let before = connected;
+            for child in children {
+                match self.add_blocks(vec![child]) {
+                    Ok(()) => {
+                        connected += 1;
+                        break;
+                    }
+                    Err(e) => debug!("Блок-сирота отклонён: {}", e),
+                }
+            }
+            if connected == before {
+                break;
+            }
+        }
+        connected
+    }
+
+    /// Добавляет пакет готовых блоков (например, полученных от пира) по
+    /// принципу «всё или ничего».
+    ///
+    /// Каждый блок проверяется относительно предыдущего так же, как при
+    /// `validate`, а его транзакции — как при `validate_transactions`
+    /// по состоянию после предыдущих блоков пакета. Если хотя бы один блок
+    /// некорректен, цепочка остаётся без изменений и возвращается первая
+    /// найденная ошибка.
+    pub fn add_blocks(&mut self, blocks: Vec<Block>) -> Result<(), ChainError> {
+        let original_len = self.blocks.len();
+        let mut state = self.snapshot_at(u64::MAX);
+        for block in blocks {
+            let index = self.blocks.len();
+            let result = self
+                .check_transaction_rules(&block.transactions, &state, index as u64, false)
+                .and_then(|()| state.apply_block(&block))
+                .and_then(|()| {
+                    self.push_block(block);
+                    self.check_block_at(index)
+                });
+            if let Err(e) = result {
+                self.truncate(original_len);
+                return Err(e);
+            }
+        }
+        if self.last_validated_len == original_len {
+            self.last_validated_len = self.blocks.len();
+        }
+        self.notify_blocks_added(original_len);
+        Ok(())
+    }
+
+    /// Первый этап синхронизации «сначала заголовки»: проверяет заголовки,
+    /// продолжающие цепочку, и запоминает их до получения тел блоков.
+    ///
+    /// Заголовки продолжают последний ранее импортированный заголовок или,
+    /// если таких нет, последний блок. У каждого проверяются индекс, ссылка
+    /// на предыдущий хеш, timestamp, хеш (функцией `config.hasher`),
+    /// заявленная сложность и контрольные точки. При ошибке ни один заголовок не запоминается.
+    pub fn import_headers(&mut self, headers: Vec<BlockHeader>) -> Result<(), ChainError> {
+        let mut previous = match self.pending_headers.last() {
+            Some(header) => *header,
+            None => self.tip().ok_or(ChainError::EmptyChain)?.header(),
+        };
+        for header in &headers {
+            let index = header.index;
+            if index != previous.index + 1 {
+                return Err(ChainError::NonMonotonicIndex { index });
+            }
+            if header.previous_hash != previous.hash {
+                return Err(ChainError::BrokenLink { index });
+            }
+            if header.timestamp < previous.timestamp
+                || (header.timestamp == previous.timestamp && !self.config.allow_equal_timestamps)
+            {
+                return Err(ChainError::TimestampNotIncreasing { index });
+            }
+            if header.hash != self.config.hasher.hash(&header.bytes()) {
+                return Err(ChainError::HashMismatch { index });
+            }
+            if leading_zero_bits(&header.hash) < header.difficulty {
+                return Err(ChainError::InsufficientWork { index });
+            }
+            self.config.check_checkpoint(index, &header.hash)?;
+            previous = *header;
+        }
+        debug!("Импортировано {} заголовков", headers.len());
+        self.pending_headers.extend(headers);
+        Ok(())
+    }
+
+    /// Второй этап синхронизации: добавляет блоки из заголовков, полученных
+    /// `import_headers`, и их тел (`bodies[i]` — транзакции i-го ожидающего
+    /// заголовка).
+    ///
+    /// Корень Меркла каждого тела сверяется с заголовком, затем блоки
+    /// добавляются как `add_blocks` — по принципу «всё или ничего». Тел может
+    /// быть меньше, чем заголовков: остальные заголовки ждут следующего вызова.
+    pub fn attach_bodies(&mut self, bodies: Vec<Vec<Transaction>>) -> Result<(), ChainError> {
+        let mut blocks = Vec::with_capacity(bodies.len());
+        for (i, transactions) in bodies.into_iter().enumerate() {
+            let Some(header) = self.pending_headers.get(i) else {
+                let index = self.blocks.len() as u64 + i as u64;
+                return Err(ChainError::MissingHeader { index });
+            };
+            if merkle_root(&transactions) != header.merkle_root {
+                return Err(ChainError::MerkleRootMismatch {
+                    index: header.index,
+                });
+            }
+            blocks.push(Block {
+                index: header.index,
+                timestamp: header.timestamp,
+                address_filter: address_filter(&transactions),
+                transactions,
+                merkle_root: header.merkle_root,
+                previous_hash: header.previous_hash,
+                nonce: header.nonce,
+                difficulty: header.difficulty,
+                hash: header.hash,
+                metadata: Vec::new(),
+            });
+        }
+        let count = blocks.len();
+        self.add_blocks(blocks)?;
+        self.pending_headers.drain(..count);
+        Ok(())
+    }
+
+    /// Заголовки, импортированные `import_headers` и ещё не получившие тел.
+    pub fn pending_headers(&self) -> &[BlockHeader] {
+        &self.pending_headers
+    }
+
+    /// Удаляет блоки начиная с позиции `len` вместе с их записями в индексе.
+    fn truncate(&mut self, len: usize) {
+        for block in self.blocks.drain(len..) {
+            self.hash_index.remove(&block.hash);
+        }
+        self.last_validated_len = self.last_validated_len.min(len);
+        self.balance_cache = OnceLock::new();
+    }
+
+    /// Проверка последнего блока относительно предыдущего: при ошибке блок
+    /// удаляется, иначе граница проверенной части сдвигается на него.
+    fn check_last_block(&mut self) -> Result<(), ChainError> {
+        let i = self.blocks.len() - 1;
+        if let Err(e) = self.check_block_at(i) {
+            self.truncate(i);
+            return Err(e);
+        }
+        if self.last_validated_len == i {
+            self.last_validated_len = i + 1;
+        }
+        Ok(())
+    }
+
+    /// Хеш генезис-блока, определяющий сеть, к которой относится цепочка.
+    ///
+    /// Паникует, если цепочка пуста (корректная цепочка всегда содержит генезис).
+    pub fn genesis_hash(&self) -> [u8; 32] {
+        self.blocks[0].hash
+    }
+
+    /// Имя сети из метаданных генезис-блока (см. `new_named`).
+    pub fn name(&self) -> Option<&str> {
+        self.blocks.first()?.metadata_str()
+    }
+
+    /// Идентификатор сети: hex-представление хеша генезис-блока.
+    pub fn chain_id(&self) -> String {
+        hex::encode(self.genesis_hash())
+    }
+
+    /// Суммарная работа цепочки: сумма `2^difficulty` по всем блокам
+    /// (с насыщением при переполнении `u128`).
+    pub fn total_work(&self) -> u128 {
+        self.blocks
+            .iter()
+            .map(|block| 1u128.checked_shl(block.difficulty).unwrap_or(u128::MAX))
+            .fold(0u128, u128::saturating_add)
+    }
+
+    /// Замена цепочки конкурирующей по правилу наибольшей суммарной работы.
+    ///
+    /// Кандидат с другим генезис-блоком отклоняется с `GenesisMismatch`,
+    /// а для пустой текущей цепочки возвращается `EmptyChain`.
+    /// Кандидат проверяется по правилам текущей цепочки. Возвращает `Ok(true)`,
+    /// если кандидат корректен и имеет большую `total_work` (и цепочка заменена),
+    /// `Ok(false)`, если его работа не больше (при равенстве остаётся текущая
+    /// цепочка), и ошибку, если кандидат некорректен. Для блоков без майнинга
+    /// работа каждого равна 1, т. е. правило сводится к самой длинной цепочке.
+    ///
+    /// Удалённая часть и снимок состояния кандидата не принимаются. Если
+    /// текущая цепочка сокращена `prune_before`, кандидат должен совпадать
+    /// с ней до границы (иначе `PrunedHistoryMismatch`), а его блоки ниже
+    /// границы проверяются как удалённые со снимком текущей цепочки.
+    pub fn try_replace(&mut self, mut candidate: Blockchain) -> Result<bool, ChainError> {
+        let expected = self.blocks.first().ok_or(ChainError::EmptyChain)?.hash;
+        if candidate.blocks.first().map(|genesis| genesis.hash) != Some(expected) {
+            return Err(ChainError::GenesisMismatch { expected });
+        }
+        candidate.config = self.config.clone();
+        candidate.pruned_before = self.pruned_before;
+        candidate.pruned_state = self.pruned_state.clone();
+        if let Some(last_pruned) = (self.pruned_before as usize).checked_sub(1) {
+            if candidate.blocks.get(last_pruned).map(|block| block.hash)
+                != Some(self.blocks[last_pruned].hash)
+            {
+                return Err(ChainError::PrunedHistoryMismatch {
+                    index: last_pruned as u64,
+                });
+            }
+            for block in &mut candidate.blocks[..=last_pruned] {
+                block.transactions = Vec::new();
+            }
+        }
+        candidate.validate()?;
+        if candidate.total_work() <= self.total_work() {
+            return Ok(false);
+        }
+        self.blocks = candidate.blocks;
+        self.invalidate_caches();
+        self.last_validated_len = self.blocks.len();
+        Ok(true)
+    }
+
+    /// Индекс последнего общего блока двух цепочек (по хешу).
+    ///
+    /// Блоки сравниваются попарно от генезиса; `None` — если различаются уже
+    /// генезис-блоки или одна из цепочек пуста.
+    pub fn common_ancestor(&self, other: &Blockchain) -> Option<u64> {
+        let shared = self.shared_prefix_len(other);
+        shared
+            .checked_sub(1)
+            .map(|position| self.blocks[position].index)
+    }
+
+    /// Блоки после точки расхождения: сначала уникальные для `self`, затем
+    /// для `other`.
+    ///
+    /// Если общего предка нет, возвращаются все блоки обеих цепочек.
+    pub fn diff(&self, other: &Blockchain) -> (Vec<Block>, Vec<Block>) {
+        let shared = self.shared_prefix_len(other);
+        (
+            self.blocks[shared..].to_vec(),
+            other.blocks[shared..].to_vec(),
+        )
+    }
+
+    /// Длина общего префикса двух цепочек.
+    fn shared_prefix_len(&self, other: &Blockchain) -> usize {
+        self.blocks
+            .iter()
+            .zip(&other.blocks)
+            .take_while(|(ours, theirs)| ours.hash == theirs.hash)
+            .count()
+    }
+
+    /// Сбрасывает служебные данные, выведенные из блоков: перестраивает индекс
+    /// по хешу, забывает границу проверенной части, заголовки, ожидающие тел,
+    /// и кеш балансов (`balances_cached`).
+    ///
+    /// Вызывается при замене блоков в `try_replace`; после прямого изменения
+    /// `blocks` его нужно вызвать вручную. `balances` и `snapshot_at`
+    /// не используют кеш и всегда вычисляются по текущим блокам.
+    pub fn invalidate_caches(&mut self) {
+        self.rebuild_index();
+        self.last_validated_len = 0;
+        self.pending_headers.clear();
+        self.balance_cache = OnceLock::new();
+    }
+
+    /// Добавляет проверенный блок в конец цепочки, обновляя индекс по хешу.
+    fn push_block(&mut self, block: Block) {
+        self.hash_index.insert(block.hash, self.blocks.len());
+        if let Some(snapshot) = self.balance_cache.get_mut() {
+            snapshot
+                .apply_block(&block)
+                .expect("баланс в i128 не переполняется (см. StateSnapshot)");
+        }
+        self.blocks.push(block);
+    }
+
+    /// Проверка набора транзакций, предлагаемого для нового блока.
+    fn check_transactions(&self, transactions: &[Transaction]) -> Result<(), ChainError> {
+        let max = self.config.max_transactions_per_block;
+        if transactions.len() > max {
+            return Err(ChainError::TooManyTransactions {
+                count: transactions.len(),
+                max,
+            });
+        }
+        self.config
+            .check_coinbase(self.blocks.len() as u64, transactions)?;
+        self.validate_transactions(transactions)
+    }
+
+    /// Проверка транзакций кандидата в следующий блок по правилам
+    /// отдельных транзакций и текущему состоянию цепочки, без добавления
+    /// блока и без структурных проверок (хешей, ссылок, числа транзакций
+    /// и coinbase).
+    ///
+    /// Проверяются подписи (если требуются), нулевые суммы, переводы самому
+    /// себе, повторы, `ChainConfig::tx_validator`, nonce, переполнение суммы
+    /// и, при `overdraft_protection`, балансы отправителей. Транзакции
+    /// учитываются по порядку, поэтому перевод может тратить средства,
+    /// полученные ранее в том же наборе.
+    ///
+    /// Состояние цепочки берётся из того же кеша, что и у `balances_cached`.
+    pub fn validate_transactions(&self, transactions: &[Transaction]) -> Result<(), ChainError> {
+        let state = self
+            .balance_cache
+            .get_or_init(|| self.snapshot_at(u64::MAX));
+        self.check_transaction_rules(transactions, state, self.blocks.len() as u64, true)
+    }
+
+    /// Правила `validate_transactions` для транзакций блока `index`
+    /// при состоянии `state` перед этим блоком.
+    ///
+    /// Подписи проверяются только при `verify_signatures`: при проверке
+    /// готовых блоков их уже проверяет `check_block_at`.
+    fn check_transaction_rules(
+        &self,
+        transactions: &[Transaction],
+        state: &StateSnapshot,
+        index: u64,
+        verify_signatures: bool,
+    ) -> Result<(), ChainError> {
+        let mut seen = HashSet::new();
+        let mut nonces: HashMap<[u8; 32], u64> = HashMap::new();
+        for (position, tx) in transactions.iter().enumerate() {
+            if verify_signatures && self.config.requires_signature(tx) && !tx.verify() {
+                return Err(ChainError::InvalidSignature { index, position });
+            }
+            if tx.amount == 0 {
+                return Err(ChainError::ZeroAmount { position });
+            }
+            if tx.from == tx.to && !tx.is_coinbase() {
+                return Err(ChainError::SelfTransfer { position });
+            }
+            if !seen.insert(tx.txid()) {
+                return Err(ChainError::DuplicateTransaction { position });
+            }
+            if tx.is_coinbase() {
+                continue;
+            }
+            self.config.tx_validator.validate(tx, self)?;
+            if let Some(&last) = nonces.get(&tx.from).or_else(|| state.nonces.get(&tx.from))
+                && tx.nonce <= last
+            {
+                return Err(match last.checked_add(1) {
+                    Some(expected) => ChainError::BadNonce {
+                        addr: tx.from,
+                        expected,
+                        got: tx.nonce,
+                    },
+                    None => ChainError::NonceExhausted { addr: tx.from },
+                });
+            }
+            nonces.insert(tx.from, tx.nonce);
+        }
+        if total_amount(transactions).is_none() {
+            return Err(ChainError::AmountOverflow);
+        }
+        if self.config.overdraft_protection {
+            let mut balances: HashMap<[u8; 32], i128> = HashMap::new();
+            let balance = |balances: &HashMap<[u8; 32], i128>, addr: &[u8; 32]| {
+                balances
+                    .get(addr)
+                    .or_else(|| state.balances.get(addr))
+                    .copied()
+                    .unwrap_or(0)
+            };
+            for tx in transactions {
+                if tx.is_coinbase() {
+                    let to = balance(&balances, &tx.to) + tx.amount as i128;
+                    balances.insert(tx.to, to);
+                    continue;
+                }
+                let available = balance(&balances, &tx.from);
+                let needed = tx.amount as i128 + tx.fee as i128;
+                if available < needed {
+                    return Err(ChainError::InsufficientFunds {
+                        addr: tx.from,
+                        needed: tx.amount.saturating_add(tx.fee),
+                        available,
+                    });
+                }
+                balances.insert(tx.from, available - needed);
+                let to = balance(&balances, &tx.to) + tx.amount as i128;
+                balances.insert(tx.to, to);
+            }
+        }
+        Ok(())
+    }
+
+    /// Добавляет новый блок, намайненный со сложностью из `next_difficulty`.
+    pub fn mine_block(&mut self, transactions: Vec<Transaction>) {
+        let difficulty = self.next_difficulty();
+        self.add_block_pow(transactions, difficulty as usize);
+    }
+
+    /// Проверка размера сериализованного блока по `max_block_bytes`.
+    fn check_block_size(&self, block: &Block) -> Result<(), ChainError> {
+        let Some(max) = self.config.max_block_bytes else {
+            return Ok(());
+        };
+        let size = serialize_block(block)?.len();
+        if size > max {
+            return Err(ChainError::BlockTooLarge {
+                index: block.index,
+                size,
+                max,
+            });
+        }
+        Ok(())
+    }
+
+    /// Проверка интервала между блоком и предыдущим по `min_block_interval`
+    /// и `max_block_interval`.
+    fn check_block_interval(&self, block: &Block, previous: &Block) -> Result<(), ChainError> {
+        let interval = block.timestamp.saturating_sub(previous.timestamp);
+        if let Some(min) = self.config.min_block_interval
+            && interval < min
+        {
+            return Err(ChainError::BlockTooSoon {
+                index: block.index,
+                interval,
+                min,
+            });
+        }
+        if let Some(max) = self.config.max_block_interval
+            && interval > max
+        {
+            return Err(ChainError::BlockTooLate {
+                index: block.index,
+                interval,
+                max,
+            });
+        }
+        Ok(())
+    }
+
+    /// Сложность, которую должен иметь следующий блок.
+    ///
+    /// Если параметры майнинга не заданы, используются значения по умолчанию.
+    pub fn next_difficulty(&self) -> u32 {
+        let params = self.config.mining.clone().unwrap_or_default();
+        expected_difficulty(&self.blocks, &params)
+    }
+
+    /// Итератор по блокам цепочки от генезиса к вершине.
+    pub fn iter(&self) -> std::slice::Iter<'_, Block> {
+        self.blocks.iter()
+    }
+
+    /// Последний блок цепочки (`None` для пустой цепочки).
+    pub fn tip(&self) -> Option<&Block> {
+        self.blocks.last()
+    }
+
+    /// Индекс последнего блока (`None` для пустой цепочки).
+    pub fn height(&self) -> Option<u64> {
+        self.tip().map(|block| block.index)
+    }
+
+    /// Количество блоков в цепочке (включая генезис).
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Проверка, что цепочка не содержит ни одного блока.
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    /// Балансы всех адресов, полученные воспроизведением транзакций цепочки,
+    /// включая начальное распределение в генезис-блоке: `to` зачисляется сумма,
+    /// с `from` — списываются сумма и комиссия (кроме coinbase-транзакций,
+    /// выпускающих монеты).
+    ///
+    /// В этой учебной модели нет проверки наличия средств, поэтому балансы
+    /// могут быть отрицательными.
+    pub fn balances(&self) -> HashMap<[u8; 32], i128> {
+        self.snapshot_at(u64::MAX).balances
+    }
+
+    /// Те же балансы, что и `balances`, без пересчёта по всей цепочке.
+    ///
+    /// Первое обращение вычисляет их от генезиса, после чего добавление
+    /// блока учитывает только его транзакции. После прямого изменения
+    /// `blocks` нужно вызвать `invalidate_caches`.
+    pub fn balances_cached(&self) -> &HashMap<[u8; 32], i128> {
+        &self
+            .balance_cache
+            .get_or_init(|| self.snapshot_at(u64::MAX))
+            .balances
+    }
+
+    /// Снимок балансов после блока `index` (или после последнего блока,
+    /// если `index` больше высоты цепочки).
+    ///
+    /// Для блоков до последнего удалённого (`prune_before`) снимок
+    /// не восстановить: в нём учитываются только оставшиеся транзакции.
+    pub fn snapshot_at(&self, index: u64) -> StateSnapshot {
+        let (mut snapshot, start) = if self.pruned_before > 0 && index >= self.pruned_state.index {
+            (self.pruned_state.clone(), self.pruned_before)
+        } else {
+            (StateSnapshot::default(), 0)
+        };
+        for block in self
+            .blocks
+            .iter()
+            .skip_while(|block| block.index < start)
+            .take_while(|block| block.index <= index)
+        {
+            snapshot
+                .apply_block(block)
+                .expect("баланс в i128 не переполняется (см. StateSnapshot)");
+        }
+        snapshot
+    }
+
+    /// Баланс одного адреса (0, если адрес не участвовал в транзакциях).
+    pub fn balance_of(&self, addr: &[u8; 32]) -> i128 {
+        self.balances().get(addr).copied().unwrap_or(0)
+    }
+
+    /// Последние использованные nonce для каждого отправителя (включая
+    /// удалённые `prune_before` транзакции).
+    fn last_nonces(&self) -> HashMap<[u8; 32], u64> {
+        let mut nonces = self.pruned_state.nonces.clone();
+        for tx in self
+            .blocks
+            .iter()
+            .skip_while(|block| block.index < self.pruned_before)
+            .flat_map(|b| &b.transactions)
+        {
+            if !tx.is_coinbase() {
+                nonces.insert(tx.from, tx.nonce);
+            }
+        }
+        nonces
+    }
+
+    /// Наименьший nonce, который примет цепочка от отправителя `addr`
+    /// (`None`, если отправитель уже использовал `u64::MAX`).
+    pub fn next_nonce(&self, addr: &[u8; 32]) -> Option<u64> {
+        self.last_nonces()
+            .get(addr)
+            .map_or(Some(0), |nonce| nonce.checked_add(1))
+    }
+
+    /// Записывает цепочку в формате bincode в `writer` без промежуточного буфера.
+    pub fn write_to<W: Write>(&self, writer: W) -> Result<(), ChainError> {
+        bincode::serialize_into(writer, self)?;
+        Ok(())
+    }
+
+    /// Читает цепочку в формате bincode из `reader` и проверяет её целостность.
+    pub fn read_from<R: Read>(reader: R) -> Result<Self, ChainError> {
+        let chain: Self = decode(reader, MAX_DESERIALIZE_BYTES)?;
+        chain.validate()?;
+        Ok(chain)
+    }
+
+    /// Сохраняет цепочку в файл в формате bincode.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save_to_file(&self, path: impl AsRef<Path>) -> Result<(), ChainError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Загружает цепочку из файла и проверяет её целостность.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load_from_file(path: impl AsRef<Path>) -> Result<Self, ChainError> {
+        Self::read_from(BufReader::new(File::open(path)?))
+    }
+
+    /// Проверяет, что последний блок не опережает часы `clock` больше чем
+    /// на `tolerance_nanos`.
+    ///
+    /// Полезна после загрузки цепочки: если её tip из будущего (расхождение
+    /// часов или подделка), новые блоки получат timestamp tip'а, а не текущее
+    /// время. Пустая цепочка проверку проходит.
+    pub fn check_not_future(
+        &self,
+        clock: &dyn Clock,
+        tolerance_nanos: u64,
+    ) -> Result<(), ChainError> {
+        let Some(tip) = self.tip() else {
+            return Ok(());
+        };
+        let now = clock.now_nanos();
+        if tip.timestamp.saturating_sub(now) > tolerance_nanos {
+            return Err(ChainError::FutureTimestamp {
+                index: tip.index,
+                timestamp: tip.timestamp,
+                now,
+            });
+        }
+        Ok(())
+    }
+
+    /// Метод вывода информации о блоках.
+    pub fn print_chain(&self) {
+        print!("{}", self);
+    }
+
+    /// Заголовки всех блоков цепочки (для лёгких клиентов).
+    pub fn headers(&self) -> Vec<BlockHeader> {
+        self.blocks.iter().map(Block::header).collect()
+    }
+
+    /// Метод вывода информации о блоке по номеру.
+    pub fn get_block(&self, index: usize) -> Option<&Block> {
+        self.blocks.get(index)
+    }
+
+    /// Блоки с индексами из полуинтервала `[start, end)`, обрезанного по
+    /// границам цепочки; при `start >= end` или `start` за концом — пустой срез.
+    pub fn range(&self, start: u64, end: u64) -> &[Block] {
+        let len = self.blocks.len();
+        let clamp = |i: u64| usize::try_from(i).map_or(len, |i| i.min(len));
+        let (start, end) = (clamp(start), clamp(end));
+        if start >= end {
+            return &[];
+        }
+        &self.blocks[start..end]
+    }
+
+    /// Страница `page` (с нуля) по `per_page` блоков; последняя страница может
+    /// быть неполной, страницы за концом цепочки и `per_page == 0` пусты.
+    pub fn page(&self, page: usize, per_page: usize) -> &[Block] {
+        let Some(start) = page.checked_mul(per_page) else {
+            return &[];
+        };
+        let end = start.saturating_add(per_page);
+        self.range(start as u64, end as u64)
+    }
+
+    /// Поиск блока по хешу.
+    ///
+    /// Сначала используется индекс по хешу; если его нет или он устарел
+    /// (например, после десериализации), выполняется линейный поиск.
+    pub fn get_block_by_hash(&self, hash: &[u8; 32]) -> Option<&Block> {
+        if let Some(block) = self
+            .hash_index
+            .get(hash)
+            .and_then(|&position| self.blocks.get(position))
+            .filter(|block| block.hash == *hash)
+        {
+            return Some(block);
+        }
+        self.blocks.iter().find(|block| block.hash == *hash)
+    }
+
+    /// Проверка, что блок с таким хешем есть в цепочке.
+    pub fn contains_hash(&self, hash: &[u8; 32]) -> bool {
+        self.get_block_by_hash(hash).is_some()
+    }
+
+    /// Путь от блока с хешем `hash` к генезису: хеши блока и всех его
+    /// предков, связанных через `previous_hash`, заканчивая генезис-хешем.
+    ///
+    /// Возвращает `None`, если блока нет в цепочке или какая-то из ссылок
+    /// на пути к генезису разорвана.
+    pub fn ancestry(&self, hash: &[u8; 32]) -> Option<Vec<[u8; 32]>> {
+        let position = self.blocks.iter().position(|block| block.hash == *hash)?;
+        let mut path = vec![*hash];
+        for pair in self.blocks[..=position].windows(2).rev() {
+            if pair[1].previous_hash != pair[0].hash {
+                return None;
+            }
+            path.push(pair[0].hash);
+        }
+        Some(path)
+    }
+
+    /// Поиск транзакции по идентификатору (`Transaction::txid`): возвращает
+    /// индекс блока и позицию транзакции в нём.
+    pub fn find_transaction(&self, tx: &Transaction) -> Option<(u64, usize)> {
+        self.locate_transaction(&tx.txid())
+    }
+
+    /// Поиск транзакции по идентификатору `txid`.
+    pub fn get_transaction_by_id(&self, txid: &[u8; 32]) -> Option<&Transaction> {
+        let (index, position) = self.locate_transaction(txid)?;
+        self.blocks[index as usize].transactions.get(position)
+    }
+
+    /// Индекс блока и позиция в нём транзакции с идентификатором `txid`.
+    fn locate_transaction(&self, txid: &[u8; 32]) -> Option<(u64, usize)> {
+        self.blocks.iter().enumerate().find_map(|(i, block)| {
+            block
+                .transactions
+                .iter()
+                .position(|candidate| candidate.txid() == *txid)
+                .map(|position| (i as u64, position))
+        })
+    }
+
+    /// Транзакции всех блоков, кроме генезиса, вместе с индексами содержащих
+    /// их блоков.
+    pub fn all_transactions(&self) -> impl Iterator<Item = (u64, &Transaction)> {
+        self.blocks
+            .iter()
+            .skip(1)
+            .flat_map(|block| block.transactions.iter().map(move |tx| (block.index, tx)))
+    }
+
+    /// Все транзакции, в которых адрес является отправителем или получателем,
+    /// вместе с индексами содержащих их блоков.
+    ///
+    /// Блоки, фильтр адресов которых исключает `addr`, не просматриваются.
+    pub fn transactions_for_address(&self, addr: &[u8; 32]) -> Vec<(u64, &Transaction)> {
+        self.blocks
+            .iter()
+            .filter(|block| block.may_contain_address(addr))
+            .flat_map(|block| block.transactions.iter().map(move |tx| (block.index, tx)))
+            .filter(|(_, tx)| tx.from == *addr || tx.to == *addr)
+            .collect()
+    }
+
+    /// Метод вывода общей информации о блокчейне.
+    ///
+    /// Для пустой цепочки вместо последнего хеша выводится «нет».
+    pub fn get_chain_info(&self) -> String {
+        let last_hash = self.tip().map_or_else(
+            || "нет".to_string(),
+            |block| hex::encode(block.hash)[..10].to_string(),
+        );
+        format!(
+            "Блоков: {}, Валидно: {}, Последний хеш: {}",
+            self.blocks.len(),
+            self.is_valid(),
+            last_hash
+        )
+    }
+
+    /// Время от генезиса до последнего блока в наносекундах
+    /// (`None`, если в цепочке меньше двух блоков).
+    fn elapsed(&self) -> Option<u64> {
+        match self.blocks.as_slice() {
+            [first, .., last] => Some(last.timestamp.saturating_sub(first.timestamp)),
+            _ => None,
+        }
+    }
+
+    /// Средний интервал между соседними блоками в наносекундах
+    /// (`None`, если в цепочке меньше двух блоков).
+    pub fn average_block_interval(&self) -> Option<u64> {
+        Some(self.elapsed()? / (self.blocks.len() as u64 - 1))
+    }
+
+    /// Пропускная способность: транзакции блоков после генезиса, делённые
+    /// на время от генезиса до последнего блока в секундах.
+    ///
+    /// Возвращает 0, если в цепочке меньше двух блоков или время не прошло.
+    pub fn transactions_per_second(&self) -> f64 {
+        let elapsed = self.elapsed().unwrap_or(0);
+        if elapsed == 0 {
+            return 0.0;
+        }
+        let transactions: usize = self.blocks[1..].iter().map(|b| b.transactions.len()).sum();
+        transactions as f64 * NANOS_PER_SEC as f64 / elapsed as f64
+    }
+
+    /// Оценка хешрейта сети в хешах в секунду по последним `HASHRATE_WINDOW`
+    /// интервалам: на блок сложности `d` в среднем уходит `2^d` хешей.
+    ///
+    /// Возвращает `None`, если в цепочке меньше двух блоков или между первым
+    /// и последним блоком окна не прошло времени.
+    pub fn estimated_hashrate(&self) -> Option<f64> {
+        let start = self.blocks.len().saturating_sub(HASHRATE_WINDOW + 1);
+        let [first, rest @ ..] = &self.blocks[start..] else {
+            return None;
+        };
+        let elapsed = rest.last()?.timestamp.saturating_sub(first.timestamp);
+        if elapsed == 0 {
+            return None;
+        }
+        let hashes: f64 = rest
+            .iter()
+            .map(|block| 2f64.powi(block.difficulty as i32))
+            .sum();
+        Some(hashes * NANOS_PER_SEC as f64 / elapsed as f64)
+    }
+
+    /// Метод сбора сводной статистики о цепочке.
+    pub fn stats(&self) -> ChainStats {
+        let serialized_total: usize = self
+            .blocks
+            .iter()
+            .map(|block| serialize_block(block).map_or(0, |bytes| bytes.len()))
+            .sum();
+        ChainStats {
+            block_count: self.blocks.len(),
+            transaction_count: self.all_transactions().count(),
+            total_amount: self
+                .all_transactions()
+                .map(|(_, tx)| tx.amount as u128)
+                .sum(),
+            average_block_size_bytes: if self.blocks.is_empty() {
+                0
+            } else {
+                serialized_total / self.blocks.len()
+            },
+            last_hash: self.tip().map(|block| block.hash),
+            is_valid: self.is_valid(),
+        }
+    }
+
+    /// Проверка целостности всей цепочки.
+    pub fn is_valid(&self) -> bool {
+        self.validate().is_ok()
+    }
+
+    /// Проверка целостности цепочки с указанием первой найденной проблемы.
+    ///
+    /// Сначала проверяется структура всех блоков, затем транзакции каждого
+    /// блока по правилам `validate_transactions` относительно состояния
+    /// перед ним.
+    pub fn validate(&self) -> Result<(), ChainError> {
+        // Проверка генезис-блока
+        let genesis = self.blocks.first().ok_or(ChainError::EmptyChain)?;
+        self.check_genesis_block(genesis)?;
+        // Проверка остальных блоков
+        for i in 1..self.blocks.len() {
+            self.check_block_at(i)
+                .inspect_err(|e| debug!("Цепочка не прошла проверку: {}", e))?;
+        }
+        self.check_transaction_history(1)
+            .inspect_err(|e| debug!("Цепочка не прошла проверку: {}", e))?;
+        trace!("Цепочка из {} блоков прошла проверку", self.blocks.len());
+        Ok(())
+    }
+
+    /// Параллельная проверка целостности цепочки.
+    ///
+    /// Самодостаточные проверки блоков (хеш, корень Меркла, подписи, сложность)
+    /// выполняются параллельно, после чего связи между блоками проверяются
+    /// одним последовательным проходом, как и транзакции по состоянию цепочки.
+    /// Результат совпадает с `is_valid`.
+    #[cfg(feature = "rayon")]
+    pub fn is_valid_parallel(&self) -> bool {
+        use rayon::prelude::*;
+
+        let Some(genesis) = self.blocks.first() else {
+            return false;
+        };
+        if self.check_genesis_block(genesis).is_err() {
+            return false;
+        }
+        let config = &self.config;
+        let pruned_before = self.pruned_before;
+        let contents_ok = self.blocks[1..].par_iter().all(|block| {
+            let has_body = block.index >= pruned_before || !block.transactions.is_empty();
+            config.check_checkpoint(block.index, &block.hash).is_ok()
+                && block
+                    .verify_contents(config.hasher.as_ref(), has_body)
+                    .is_ok()
+                && (!has_body
+                    || config
+                        .check_coinbase(block.index, &block.transactions)
+                        .is_ok()
+                        && config.check_signatures(block).is_ok())
+        });
+        contents_ok
+            && (1..self.blocks.len()).all(|i| {
+                self.blocks[i]
+                    .verify_link(&self.blocks[i - 1], self.config.allow_equal_timestamps)
+                    .is_ok()
+                    && self.check_expected_difficulty(i).is_ok()
+            })
+            && self.check_transaction_history(1).is_ok()
+    }
+
+    /// Проверяет только блоки, добавленные после последней проверки,
+    /// и сдвигает границу проверенной части цепочки. Транзакции новых блоков
+    /// проверяются по состоянию перед ними, как при `validate`; при ошибке
+    /// граница не сдвигается.
+    ///
+    /// Уже проверенные блоки повторно не проверяются, поэтому подмена старых
+    /// блоков этим методом не обнаруживается — для этого нужен полный `validate`.
+    pub fn validate_incremental(&mut self) -> Result<(), ChainError> {
+        if self.blocks.is_empty() {
+            return Err(ChainError::EmptyChain);
+        }
+        if self.last_validated_len == 0 {
+            self.check_genesis_block(&self.blocks[0])?;
+            self.last_validated_len = 1;
+        }
+        let from = self.last_validated_len;
+        for i in from..self.blocks.len() {
+            self.check_block_at(i)?;
+        }
+        self.check_transaction_history(from)?;
+        self.last_validated_len = self.blocks.len();
+        Ok(())
+    }
+
+    /// Проверка транзакций блоков начиная с `from > 0` по правилам
+    /// `validate_transactions` относительно состояния перед каждым блоком.
+    ///
+    /// Блоки ниже `pruned_before` пропускаются: их состояние берётся
+    /// из снимка `prune_before`. Структура и подписи блоков должны быть уже
+    /// проверены (`check_block_at`).
+    fn check_transaction_history(&self, from: usize) -> Result<(), ChainError> {
+        let start = from.max(self.pruned_before as usize);
+        if start >= self.blocks.len() {
+            return Ok(());
+        }
+        let mut state = self.snapshot_at(start as u64 - 1);
+        for block in &self.blocks[start..] {
+            self.check_transaction_rules(&block.transactions, &state, block.index, false)?;
+            state.apply_block(block)?;
+        }
+        Ok(())
+    }
+
+    /// Проверка генезис-блока: структура, хеш и (если тело не удалено)
+    /// корень Меркла и фильтр адресов.
+    fn check_genesis_block(&self, genesis: &Block) -> Result<(), ChainError> {
+        check_genesis(genesis, self.config.hasher.as_ref())?;
+        self.config.check_checkpoint(0, &genesis.hash)?;
+        if genesis.difficulty != self.config.genesis_difficulty {
+            return Err(ChainError::DifficultyMismatch {
+                index: 0,
+                expected: self.config.genesis_difficulty,
+                actual: genesis.difficulty,
+            });
+        }
+        if !genesis.meets_difficulty() {
+            return Err(ChainError::InsufficientWork { index: 0 });
+        }
+        if self.is_pruned(genesis) {
+            return Ok(());
+        }
+        if !genesis.verify_merkle_root() {
+            return Err(ChainError::MerkleRootMismatch { index: 0 });
+        }
+        if !genesis.verify_address_filter() {
+            return Err(ChainError::AddressFilterMismatch { index: 0 });
+        }
+        Ok(())
+    }
+
+    /// Полная проверка блока `i > 0` относительно предыдущего.
+    ///
+    /// У блоков с удалёнными транзакциями корень Меркла и подписи
+    /// не проверяются (см. `is_pruned`).
+    fn check_block_at(&self, i: usize) -> Result<(), ChainError> {
+        trace!("Проверка блока #{}", i);
+        let block = &self.blocks[i];
+        let has_body = !self.is_pruned(block);
+        self.config.check_checkpoint(block.index, &block.hash)?;
+        block.verify_link(&self.blocks[i - 1], self.config.allow_equal_timestamps)?;
+        block.verify_contents(self.config.hasher.as_ref(), has_body)?;
+        if has_body {
+            self.config
+                .check_coinbase(block.index, &block.transactions)?;
+            self.config.check_signatures(block)?;
+        }
+        self.check_expected_difficulty(i)
+    }
+
+    /// Удалены ли транзакции блока методом `prune_before`.
+    ///
+    /// Блок ниже границы, у которого остались транзакции, удалённым
+    /// не считается и проверяется целиком.
+    fn is_pruned(&self, block: &Block) -> bool {
+        block.index < self.pruned_before && block.transactions.is_empty()
+    }
+
+    /// Удаляет транзакции всех блоков с индексом меньше `index`, сохраняя их
+    /// заголовки (индекс, timestamp, корень Меркла и хеши).
+    ///
+    /// Связность цепочки и хеши удалённых блоков по-прежнему проверяются,
+    /// а корень Меркла и подписи — нет. Балансы и nonce удалённых транзакций
+    /// сохраняются в снимке, поэтому балансы не меняются, а повтор старой
+    /// транзакции по-прежнему отклоняется; поиск транзакций учитывает только
+    /// оставшиеся. `index` больше длины цепочки обрезается до неё, повторный
+    /// вызов с меньшим индексом ничего не меняет.
+    ///
+    /// Снимок сохраняется вместе с цепочкой и при загрузке принимается
+    /// на доверии: без удалённых транзакций его не проверить, поэтому
+    /// сокращённую цепочку стоит читать только из собственного хранилища.
+    /// `try_replace` снимок и удалённую часть кандидата не принимает.
+    pub fn prune_before(&mut self, index: u64) {
+        let index = index.min(self.blocks.len() as u64);
+        if index <= self.pruned_before {
+            return;
+        }
+        self.pruned_state = self.snapshot_at(index - 1);
+        for block in self
+            .blocks
+            .iter_mut()
+            .take_while(|block| block.index < index)
+        {
+            block.transactions = Vec::new();
+        }
+        self.pruned_before = index;
+        self.balance_cache = OnceLock::new();
+    }
+
+    /// Индекс, до которого у блоков удалены транзакции (0 — ничего не удалено).
+    pub fn pruned_before(&self) -> u64 {
+        self.pruned_before
+    }
+
+    /// Проверка, что сложность блока `i` совпадает с вычисленной по истории.
+    fn check_expected_difficulty(&self, i: usize) -> Result<(), ChainError> {
+        let Some(params) = &self.config.mining else {
+            return Ok(());
+        };
+        let current = &self.blocks[i];
+        let expected = expected_difficulty(&self.blocks[..i], params);
+        if current.difficulty != expected {
+            return Err(ChainError::DifficultyMismatch {
+                index: current.index,
+                expected,
+                actual: current.difficulty,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Модель участников сети (пиров) и консенсуса.
+///
+/// Идентификатор пира.
+pub type PeerId = u32;
+
+/// Моделирование пира.
+#[derive(Debug, Clone)]
+pub struct Peer {
+    pub id: PeerId,
+    pub is_honest: bool,
+    /// Номер раунда голосования (с 1), начиная с которого честный пир
+    /// голосует «за»; в более ранних раундах он голосует «против».
+    pub approves_from_round: u32,
+}
+
+impl Peer {
+    pub fn new(id: PeerId) -> Self {
+        Self {
+            id,
+            is_honest: true,
+            approves_from_round: 1,
+        }
+    }
+
+    /// Создание нечестного пира, голосующего против любых транзакций.
+    pub fn dishonest(id: PeerId) -> Self {
+        Self {
+            id,
+            is_honest: false,
+            approves_from_round: 1,
+        }
+    }
+
+    /// Создание честного, но «медленного» пира, который голосует «за»
+    /// только начиная с раунда `round`.
+    pub fn approving_from_round(id: PeerId, round: u32) -> Self {
+        Self {
+            approves_from_round: round,
+            ..Self::new(id)
+        }
+    }
+
+    /// Голос пира за блок с транзакциями: честные пиры голосуют «за», нечестные — «против».
+    pub fn vote_for_transaction(&self, transactions: &[Transaction]) -> bool {
+        self.vote_in_round(transactions, 1)
+    }
+
+    /// Голос пира в раунде `round` (с 1) многораундового голосования.
+    pub fn vote_in_round(&self, _transactions: &[Transaction], round: u32) -> bool {
+        let vote = self.is_honest && round >= self.approves_from_round;
+        trace!(
+            "Пир {} в раунде {} голосует {}",
+            self.id,
+            round,
+            if vote { "за" } else { "против" }
+        );
+        vote
+    }
+}
+
+/// Механизм консенсуса, решающий, можно ли добавить блок с транзакциями.
+///
+/// Реализации могут моделировать голосование пиров, proof-of-work,
+/// proof-of-authority и т. п.
+pub trait Consensus {
+    /// Одобряет или отклоняет блок с заданными транзакциями.
+    fn approve(&self, transactions: &[Transaction], chain: &Blockchain) -> bool;
+
+    /// Предлагает добавить блок с транзакциями.
+    ///
+    /// Блок добавляется, если консенсус его одобрил и транзакции прошли
+    /// проверку `try_add_block`. Возвращает `true`, если блок добавлен.
+    fn propose_block(&self, transactions: Vec<Transaction>, blockchain: &mut Blockchain) -> bool {
+        matches!(self.try_propose_block(transactions, blockchain), Ok(true))
+    }
+
+    /// То же, что `propose_block`, но с причиной отказа.
+    ///
+    /// Возвращает `Ok(false)`, если консенсус не одобрил блок, и ошибку, если
+    /// блок нельзя добавить: для пустой цепочки — `ChainError::EmptyChain`
+    /// (без голосования), иначе — ошибку `try_add_block`.
+    fn try_propose_block(
+        &self,
+        transactions: Vec<Transaction>,
+        blockchain: &mut Blockchain,
+    ) -> Result<bool, ChainError> {
+        if blockchain.is_empty() {
+            return Err(ChainError::EmptyChain);
+        }
+        if !self.approve(&transactions, blockchain) {
+            return Ok(false);
+        }
+        blockchain.try_add_block(transactions)?;
+        Ok(true)
+    }
+}
+
+/// Итоги голосования пиров за блок.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VoteTally {
+    /// Идентификаторы пиров, проголосовавших «за».
+    pub approvals: Vec<PeerId>,
+    /// Идентификаторы пиров, проголосовавших «против».
+    pub rejections: Vec<PeerId>,
+    /// Минимальное число одобрений для принятия блока.
+    pub threshold: usize,
+}
+
+impl VoteTally {
+    /// Набрал ли блок нужное число одобрений.
+    pub fn is_approved(&self) -> bool {
+        self.approvals.len() >= self.threshold
+    }
+}
+
+/// Консенсус с фиксированным списком пиров.
+pub struct FixedPeerConsensus {
+    pub peers: Vec<Peer>,
+}
+
+impl FixedPeerConsensus {
+    pub fn new(peers: Vec<Peer>) -> Self {
+        Self { peers }
+    }
+
+    pub fn peer_count(&self) -> usize {
+        self.peers.len()
+    }
+
+    /// Минимальное число одобрений для принятия блока — строгое большинство,
+    /// т. е. больше половины пиров: 1 из 1, 2 из 2, 2 из 3, 3 из 4, 3 из 5.
+    ///
+    /// При чётном числе пиров ничья (ровно половина голосов) блок не проходит.
+    /// Крайние случаи:
+    /// - 0 пиров — порог 1 недостижим, блок никогда не проходит;
+    /// - 1 пир — достаточно его одобрения;
+    /// - 2 пира — нужны оба голоса.
+    pub fn required_approvals(&self) -> usize {
+        self.peers.len() / 2 + 1
+    }
+
+    /// Подсчёт голосов пиров за блок с транзакциями: кто проголосовал «за»,
+    /// кто «против» и сколько одобрений требуется.
+    pub fn tally(&self, transactions: &[Transaction]) -> VoteTally {
+        self.tally_round(transactions, 1)
+    }
+
+    fn tally_round(&self, transactions: &[Transaction], round: u32) -> VoteTally {
+        let (approvals, rejections) = self
+            .peers
+            .iter()
+            .partition::<Vec<_>, _>(|peer| peer.vote_in_round(transactions, round));
+        let tally = VoteTally {
+            approvals: approvals.iter().map(|peer| peer.id).collect(),
+            rejections: rejections.iter().map(|peer| peer.id).collect(),
+            threshold: self.required_approvals(),
+        };
+        debug!(
+            "Раунд {}: {} из {} за, требуется {}",
+            round,
+            tally.approvals.len(),
+            self.peers.len(),
+            tally.threshold
+        );
+        tally
+    }
+
+    /// Многораундовое голосование: если в раунде блок не набрал нужного
+    /// числа одобрений, пиры опрашиваются снова, всего не более `max_rounds` раз.
+    ///
+    /// Возвращает номер раунда, в котором блок был одобрен и добавлен, или
+    /// `None`, если одобрения не было за `max_rounds` раундов либо транзакции
+    /// не прошли проверку `try_add_block`.
+    pub fn propose_block_rounds(
+        &self,
+        transactions: Vec<Transaction>,
+        blockchain: &mut Blockchain,
+        max_rounds: u32,
+    ) -> Option<u32> {
+        let round =
+            (1..=max_rounds).find(|&round| self.tally_round(&transactions, round).is_approved())?;
+        blockchain.try_add_block(transactions).ok()?;
+        Some(round)
+    }
+}
+
+impl Consensus for FixedPeerConsensus {
+    /// Блок одобрен, если набрал строгое большинство голосов пиров
+    /// (см. [`FixedPeerConsensus::tally`]); без пиров блок не одобряется.
+    fn approve(&self, transactions: &[Transaction], _chain: &Blockchain) -> bool {
+        self.tally(transactions).is_approved()
+    }
+}
+
+/// Византийский консенсус (BFT).
+///
+/// Чтобы выдержать `f` византийских (произвольно голосующих) пиров, сеть должна
+/// состоять минимум из `3f + 1` участников, а блок — набрать `n - f` одобрений
+/// (`2f + 1` при `n = 3f + 1`): тогда даже `f` голосов «против» не мешают
+/// честным пирам принять блок, а `f + 1` голосов «против» уже не оставляют
+/// кворума.
+pub struct BftConsensus {
+    pub peers: Vec<Peer>,
+}
+
+impl BftConsensus {
+    /// Создание сети из `3f + 1` честных пиров (идентификаторы с 1),
+    /// выдерживающей `f` сбоев.
+    ///
+    /// Паникует, если `3f + 1` не помещается в `PeerId`.
+    pub fn tolerating(f: usize) -> Self {
+        let n = f
+            .checked_mul(3)
+            .and_then(|n| n.checked_add(1))
+            .and_then(|n| PeerId::try_from(n).ok())
+            .expect("число пиров 3f + 1 не помещается в PeerId");
+        let peers = (1..=n).map(Peer::new).collect();
+        Self { peers }
+    }
+
+    /// Число сбойных пиров `f`, которое выдерживает сеть: `(n - 1) / 3`.
+    pub fn fault_tolerance(&self) -> usize {
+        self.peers.len().saturating_sub(1) / 3
+    }
+
+    /// Минимальное число одобрений: `n - f`.
+    ///
+    /// При `n = 3f + 1` это `2f + 1`; при остальных `n` порог выше, чтобы
+    /// два кворума всегда пересекались больше чем в `f` пирах.
+    pub fn required_approvals(&self) -> usize {
+        self.peers.len() - self.fault_tolerance()
+    }
+}
+
+impl Consensus for BftConsensus {
+    fn approve(&self, transactions: &[Transaction], _chain: &Blockchain) -> bool {
+        if self.peers.is_empty() {
+            return false;
+        }
+        let approvals = self
+            .peers
+            .iter()
+            .filter(|peer| peer.vote_for_transaction(transactions))
+            .count();
+        debug!(
+            "Голосование BFT: {} из {} за, требуется {}",
+            approvals,
+            self.peers.len(),
+            self.required_approvals()
+        );
+        approvals >= self.required_approvals()
+    }
+}
+
+/// Консенсус с голосами, взвешенными по доле (stake) пиров.
+///
+/// Блок принимается, если суммарная доля одобривших пиров строго больше
+/// половины общей доли. Сеть с нулевой общей долей блоки не принимает.
+pub struct StakeConsensus {
+    pub peers: Vec<(Peer, u64)>,
+}
+
+impl StakeConsensus {
+    pub fn new(peers: Vec<(Peer, u64)>) -> Self {
+        Self { peers }
+    }
+
+    /// Суммарная доля всех пиров.
+    pub fn total_stake(&self) -> u128 {
+        self.peers.iter().map(|(_, stake)| *stake as u128).sum()
+    }
+}
+
+impl Consensus for StakeConsensus {
+    fn approve(&self, transactions: &[Transaction], _chain: &Blockchain) -> bool {
+        let total = self.total_stake();
+        if total == 0 {
+            return false;
+        }
+        let approving: u128 = self
+            .peers
+            .iter()
+            .filter(|(peer, _)| peer.vote_for_transaction(transactions))
+            .map(|(_, stake)| *stake as u128)
+            .sum();
+        debug!("Голосование по доле: {} из {} за", approving, total);
+        approving * 2 > total
+    }
+}
+
+/// Максимальный объём данных, который читают функции десериализации.
+///
+/// Длины коллекций в bincode задаются самими данными, поэтому без ограничения
+/// злонамеренный буфер мог бы заставить узел читать и выделять память без конца.
+pub const MAX_DESERIALIZE_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Настройки bincode для чтения: те же, что у `bincode::deserialize`,
+/// но с ограничением на объём прочитанных данных.
+fn bincode_options(limit: u64) -> impl Options {
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .with_limit(limit)
+}
+
+/// Ошибка bincode с выделением превышения лимита в `SizeLimitExceeded`.
+fn decode_error(e: bincode::Error, limit: u64) -> ChainError {
+    match *e {
+        bincode::ErrorKind::SizeLimit => ChainError::SizeLimitExceeded { limit },
+        _ => ChainError::Serialization(e),
+    }
+}
+
+/// Десериализация с ограничением в `limit` байт.
+///
+/// Читает через `Read` даже срез байтов: при чтении из среза bincode
+/// не учитывает лимит.
+fn decode<T: DeserializeOwned>(reader: impl Read, limit: u64) -> Result<T, ChainError> {
+    bincode_options(limit)
+        .deserialize_from(reader)
+        .map_err(|e| decode_error(e, limit))
+}
+
+/// Сериализация
+pub fn serialize_block(block: &Block) -> Result<Vec<u8>, bincode::Error> {
+    bincode::serialize(block)
+}
+
+pub fn deserialize_block(bytes: &[u8]) -> Result<Block, ChainError> {
+    decode(bytes, MAX_DESERIALIZE_BYTES)
+}
+
+impl Block {
+    /// Аннотированный hex-дамп блока в формате bincode (`serialize_block`).
+    ///
+    /// Каждое поле начинается строкой `-- имя (N байт) = значение`, за которой
+    /// идут строки по 16 байт со смещением от начала блока. Длины списков
+    /// (`transactions`, `metadata`) — это префиксы `u64`, записанные перед
+    /// их элементами.
+    pub fn hex_dump(&self) -> String {
+        let bytes = serialize_block(self).expect("блок сериализуется в память без ошибок");
+        let mut fields = vec![
+            ("index".to_string(), 8, self.index.to_string()),
+            ("timestamp".to_string(), 8, self.timestamp.to_string()),
+            (
+                "transactions".to_string(),
+                8,
+                format!("{} шт.", self.transactions.len()),
+            ),
+        ];
+        for (i, tx) in self.transactions.iter().enumerate() {
+            let len = bincode::serialized_size(tx).expect("транзакция сериализуется без ошибок");
+            fields.push((
+                format!("transactions[{}]", i),
+                len as usize,
+                hex::encode(tx.txid()),
+            ));
+        }
+        fields.extend([
+            ("merkle_root".to_string(), 32, hex::encode(self.merkle_root)),
+            (
+                "previous_hash".to_string(),
+                32,
+                hex::encode(self.previous_hash),
+            ),
+            ("nonce".to_string(), 8, self.nonce.to_string()),
+            ("difficulty".to_string(), 4, self.difficulty.to_string()),
+            ("hash".to_string(), 32, hex::encode(self.hash)),
+            (
+                "metadata".to_string(),
+                8 + self.metadata.len(),
+                format!("{} байт", self.metadata.len()),
+            ),
+            (
+                "address_filter".to_string(),
+                32,
+                hex::encode(self.address_filter),
+            ),
+        ]);
+
+        let mut dump = String::new();
+        let mut offset = 0;
+        for (name, len, value) in fields {
+            dump.push_str(&format!("-- {} ({} байт) = {}\n", name, len, value));
+            for row in bytes[offset..offset + len].chunks(16) {
+                let hex: Vec<String> = row.iter().map(|byte| format!("{:02x}", byte)).collect();
+                dump.push_str(&format!("{:08x}  {}\n", offset, hex.join(" ")));
+                offset += row.len();
+            }
+        }
+        debug_assert_eq!(offset, bytes.len());
+        dump
+    }
+}
+
+pub fn serialize_blockchain(chain: &Blockchain) -> Result<Vec<u8>, bincode::Error> {
+    bincode::serialize(chain)
+}
+
+/// Десериализация цепочки без проверки целостности.
+///
+/// Результат может оказаться пустым или повреждённым; для данных из
+/// недоверенных источников используйте [`deserialize_blockchain_checked`].
+///
+/// Читается не больше `MAX_DESERIALIZE_BYTES` байт; при превышении
+/// возвращается `ChainError::SizeLimitExceeded`.
+pub fn deserialize_blockchain(bytes: &[u8]) -> Result<Blockchain, ChainError> {
+    deserialize_blockchain_with_limit(bytes, MAX_DESERIALIZE_BYTES)
+}
+
+/// Десериализация цепочки без проверки целостности с ограничением
+/// на объём прочитанных данных в `limit` байт.
+pub fn deserialize_blockchain_with_limit(
+    bytes: &[u8],
+    limit: u64,
+) -> Result<Blockchain, ChainError> {
+    decode(bytes, limit)
+}
+
+/// Десериализация цепочки с полной проверкой `validate`.
+pub fn deserialize_blockchain_checked(bytes: &[u8]) -> Result<Blockchain, ChainError> {
+    let chain = deserialize_blockchain(bytes)?;
+    chain.validate()?;
+    Ok(chain)
+}
+
+/// Проверка сериализованной цепочки одним вызовом: десериализация,
+/// полная проверка `validate` и сводная статистика.
+///
+/// Любая ошибка (повреждённые байты, превышение лимита, нарушение правил
+/// цепочки) возвращается как `ChainError`.
+pub fn verify_serialized(bytes: &[u8]) -> Result<ChainStats, ChainError> {
+    Ok(deserialize_blockchain_checked(bytes)?.stats())
+}
+
+/// Журнал блоков: файл, в который блоки дописываются по одному.
+///
+/// Каждая запись — длина (`u64`, little-endian) и блок в формате bincode,
+/// поэтому добавление блока не требует перезаписи всего файла.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug)]
+pub struct BlockLog {
+    path: PathBuf,
+    file: File,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl BlockLog {
+    /// Открывает журнал для дописывания, создавая файл при необходимости.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, ChainError> {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        Ok(Self { path, file })
+    }
+
+    /// Дописывает блок в конец журнала.
+    pub fn append(&mut self, block: &Block) -> Result<(), ChainError> {
+        let bytes = serialize_block(block)?;
+        let mut entry = Vec::with_capacity(8 + bytes.len());
+        entry.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+        entry.extend_from_slice(&bytes);
+        self.file.write_all(&entry)?;
+        Ok(())
+    }
+
+    /// Восстанавливает цепочку из журнала, проверяя каждый блок по мере чтения.
+    ///
+    /// Первая запись должна быть генезис-блоком. Обрезанная последняя запись
+    /// приводит к ошибке ввода-вывода.
+    pub fn load(&self) -> Result<Blockchain, ChainError> {
+        let mut reader = BufReader::new(File::open(&self.path)?);
+        let mut chain: Option<Blockchain> = None;
+        while !reader.fill_buf()?.is_empty() {
+            let mut len = [0u8; 8];
+            reader.read_exact(&mut len)?;
+            let len = u64::from_le_bytes(len);
+            let mut bytes = Vec::new();
+            (&mut reader).take(len).read_to_end(&mut bytes)?;
+            if bytes.len() as u64 != len {
+                return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+            }
+            let block = deserialize_block(&bytes)?;
+            match &mut chain {
+                None => chain = Some(Blockchain::with_genesis(block)?),
+                Some(chain) => {
+                    chain.push_block(block);
+                    chain.check_last_block()?;
+                }
+            }
+        }
+        chain.ok_or(ChainError::EmptyChain)
+    }
+}
+
+/// Сериализация цепочки в bincode со сжатием gzip.
+///
+/// Хеши блоков сжимаются плохо, но адреса и служебные поля повторяются,
+/// поэтому сжатая цепочка заметно меньше:
+///
+/// ```
+/// use rustblockchain::{Blockchain, Transaction, serialize_blockchain,
+///     serialize_blockchain_compressed};
+///
+/// let mut chain = Blockchain::new();
+/// for i in 0..100 {
+///     chain.add_block(vec![Transaction::new([1; 32], [2; 32], 10).with_nonce(i)]);
+/// }
+/// let plain = serialize_blockchain(&chain).unwrap();
+/// let compressed = serialize_blockchain_compressed(&chain).unwrap();
+/// let ratio = plain.len() as f64 / compressed.len() as f64;
+/// println!("{} -> {} байт (в {:.1} раза)", plain.len(), compressed.len(), ratio);
+/// assert!(ratio > 1.0);
+/// ```
+#[cfg(feature = "compression")]
+pub fn serialize_blockchain_compressed(chain: &Blockchain) -> Result<Vec<u8>, ChainError> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    bincode::serialize_into(&mut encoder, chain)?;
+    Ok(encoder.finish()?)
+}
+
+/// Распаковка и десериализация цепочки, сжатой `serialize_blockchain_compressed`.
+#[cfg(feature = "compression")]
+pub fn deserialize_blockchain_compressed(bytes: &[u8]) -> Result<Blockchain, ChainError> {
+    decode(GzDecoder::new(bytes), MAX_DESERIALIZE_BYTES)
+}
+
+/// Цепочка в компактном формате: различные адреса транзакций записаны один
+/// раз в `addresses`, а транзакции ссылаются на них номерами.
+#[derive(Serialize, Deserialize)]
+struct CompactBlockchain {
+    version: u16,
+    addresses: Vec<[u8; 32]>,
+    blocks: Vec<CompactBlock>,
+    config: ChainConfig,
+    pruned_before: u64,
+    pruned_state: StateSnapshot,
+}
+
+/// Блок в компактном формате: как `Block`, но с `CompactTransaction`.
+#[derive(Serialize, Deserialize)]
+struct CompactBlock {
+    index: u64,
+    timestamp: u64,
+    transactions: Vec<CompactTransaction>,
+    merkle_root: [u8; 32],
+    previous_hash: [u8; 32],
+    nonce: u64,
+    difficulty: u32,
+    hash: [u8; 32],
+    metadata: Vec<u8>,
+    address_filter: [u8; 32],
+}
+
+/// Транзакция в компактном формате: `from` и `to` — номера в таблице адресов.
+#[derive(Serialize, Deserialize)]
+struct CompactTransaction {
+    from: u32,
+    to: u32,
+    amount: u64,
+    nonce: u64,
+    fee: u64,
+    multisig: Option<MultiSig>,
+    #[serde(with = "BigArray")]
+    signature: [u8; 64],
+}
+
+/// Таблица адресов, заполняемая при сериализации в компактный формат.
+#[derive(Default)]
+struct AddressTable {
+    addresses: Vec<[u8; 32]>,
+    positions: HashMap<[u8; 32], u32>,
+}
+
+impl AddressTable {
+    /// Номер адреса в таблице; новый адрес добавляется в конец.
+    fn intern(&mut self, address: [u8; 32]) -> u32 {
+        *self.positions.entry(address).or_insert_with(|| {
+            let index = u32::try_from(self.addresses.len())
+                .expect("число различных адресов не превышает u32::MAX");
+            self.addresses.push(address);
+            index
+        })
+    }
+}
+
+/// Сериализация цепочки в bincode с таблицей адресов.
+///
+/// Каждый различный адрес отправителя или получателя записывается один раз,
+/// а транзакции хранят его 4-байтовый номер вместо 32 байт. В отличие от
+/// gzip (`serialize_blockchain_compressed`) формат не требует распаковки
+/// и выигрывает только на повторяющихся адресах: 100 блоков с переводом
+/// между двумя адресами занимают около 27 КБ вместо 33 КБ.
+pub fn serialize_blockchain_compact(chain: &Blockchain) -> Result<Vec<u8>, bincode::Error> {
+    let mut table = AddressTable::default();
+    let blocks = chain
+        .blocks
+        .iter()
+        .map(|block| CompactBlock {
+            index: block.index,
+            timestamp: block.timestamp,
+            transactions: block
+                .transactions
+                .iter()
+                .map(|tx| CompactTransaction {
+                    from: table.intern(tx.from),
+                    to: table.intern(tx.to),
+                    amount: tx.amount,
+                    nonce: tx.nonce,
+                    fee: tx.fee,
+                    multisig: tx.multisig.clone(),
+                    signature: tx.signature,
+                })
+                .collect(),
+            merkle_root: block.merkle_root,
+            previous_hash: block.previous_hash,
+            nonce: block.nonce,
+            difficulty: block.difficulty,
+            hash: block.hash,
+            metadata: block.metadata.clone(),
+            address_filter: block.address_filter,
+        })
+        .collect();
+    bincode::serialize(&CompactBlockchain {
+        version: chain.version,
+        addresses: table.addresses,
+        blocks,
+        config: chain.config.clone(),
+        pruned_before: chain.pruned_before,
+        pruned_state: chain.pruned_state.clone(),
+    })
+}
+
+/// Десериализация цепочки, записанной `serialize_blockchain_compact`,
+/// без проверки целостности.
+///
+/// Номер адреса за пределами таблицы даёт `ChainError::UnknownAddressIndex`.
+/// Граница удалённой части и снимок состояния принимаются так же, как
+/// в `deserialize_blockchain`.
+pub fn deserialize_blockchain_compact(bytes: &[u8]) -> Result<Blockchain, ChainError> {
+    let compact: CompactBlockchain = decode(bytes, MAX_DESERIALIZE_BYTES)?;
+    let address = |index: u32| {
+        compact
+            .addresses
+            .get(index as usize)
+            .copied()
+            .ok_or(ChainError::UnknownAddressIndex { index })
+    };
+    let mut blocks = Vec::with_capacity(compact.blocks.len());
+    for block in compact.blocks {
+        let transactions = block
+            .transactions
+            .into_iter()
+            .map(|tx| {
+                Ok(Transaction {
+                    from: address(tx.from)?,
+                    to: address(tx.to)?,
+                    amount: tx.amount,
+                    nonce: tx.nonce,
+                    fee: tx.fee,
+                    multisig: tx.multisig,
+                    signature: tx.signature,
+                })
+            })
+            .collect::<Result<_, ChainError>>()?;
+        blocks.push(Block {
+            index: block.index,
+            timestamp: block.timestamp,
+            transactions,
+            merkle_root: block.merkle_root,
+            previous_hash: block.previous_hash,
+            nonce: block.nonce,
+            difficulty: block.difficulty,
+            hash: block.hash,
+            metadata: block.metadata,
+            address_filter: block.address_filter,
+        });
+    }
+    let mut chain = Blockchain::from_blocks_unchecked(blocks);
+    chain.version = compact.version;
+    chain.config = compact.config;
+    chain.restore_pruned(compact.pruned_before, compact.pruned_state);
+    Ok(chain)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{COINBASE_ADDRESS, address_filter, hash_pair, hash_transaction};
+    use crate::{
+        BlockBuilder, ChainBuilder, MultiSig, SigningKey, address_to_hex, generate_vanity_address,
+        parse_address, verify_headers, verify_merkle_proof,
+    };
+
+    fn dummy_tx(from: [u8; 32], to: [u8; 32], amount: u64) -> Transaction {
+        Transaction::new(from, to, amount)
+    }
+
+    fn signed_tx(key: &SigningKey, to: [u8; 32], amount: u64) -> Transaction {
+        let mut tx = Transaction::new(key.verifying_key().to_bytes(), to, amount);
+        tx.sign(key);
+        tx
+    }
+
+    #[test]
+    fn test_genesis_block_has_correct_properties() {
+        let chain = Blockchain::new();
+        let genesis = &chain.blocks[0];
+        assert_eq!(genesis.index, 0);
+        assert_eq!(genesis.previous_hash, [0u8; 32]);
+        assert!(genesis.transactions.is_empty());
+        assert_eq!(genesis.hash, genesis.calculate_hash());
+    }
+
+    #[test]
+    fn test_chain_validity_with_real_transactions() {
+        let mut chain = Blockchain::new();
+        chain.add_block(vec![dummy_tx([1; 32], [2; 32], 100)]);
+        chain.add_block(vec![dummy_tx([3; 32], [4; 32], 50)]);
+        assert!(chain.is_valid());
+    }
+
+    #[test]
+    fn test_chain_becomes_invalid_after_tampering() {
+        let mut chain = Blockchain::new();
+        chain.add_block(vec![dummy_tx([1; 32], [2; 32], 1)]);
+        chain.blocks[1].transactions.clear();
+        assert!(!chain.is_valid());
+    }
+
+    #[test]
+    fn test_validate_reports_hash_mismatch_index() {
+        let mut chain = Blockchain::new();
+        for i in 1..=3 {
+            chain.add_block(vec![dummy_tx([i; 32], [9; 32], 1)]);
+        }
+        chain.blocks[2].timestamp += 1;
+        assert!(matches!(
+            chain.validate(),
+            Err(ChainError::HashMismatch { index: 2 })
+        ));
+    }
+
+    #[test]
+    fn test_validate_reports_broken_link_index() {
+        let mut chain = Blockchain::new();
+        chain.add_block(vec![dummy_tx([1; 32], [2; 32], 1)]);
+        chain.add_block(vec![dummy_tx([3; 32], [4; 32], 1)]);
+        chain.blocks[2].previous_hash = [7u8; 32];
+        chain.blocks[2].hash = chain.blocks[2].calculate_hash();
+        assert!(matches!(
+            chain.validate(),
+            Err(ChainError::BrokenLink { index: 2 })
+        ));
+    }
+
+    #[test]
+    fn test_validate_reports_non_monotonic_index_and_genesis() {
+        let mut chain = Blockchain::new();
+        chain.add_block(vec![dummy_tx([1; 32], [2; 32], 1)]);
+        chain.blocks[1].index = 5;
+        assert!(matches!(
+            chain.validate(),
+            Err(ChainError::NonMonotonicIndex { index: 5 })
+        ));
+
+        let mut chain = Blockchain::new();
+        chain.blocks[0].previous_hash = [1u8; 32];
+        assert!(matches!(chain.validate(), Err(ChainError::InvalidGenesis)));
+    }
+
+    #[test]
+    fn test_mined_block_has_leading_zero_bits() {
+        let mut chain = Blockchain::new();
+        chain.add_block_pow(vec![dummy_tx([1; 32], [2; 32], 10)], 8);
+        let block = &chain.blocks[1];
+        assert_eq!(block.difficulty, 8);
+        assert_eq!(block.hash[0], 0);
+        assert!(leading_zero_bits(&block.hash) >= 8);
+        assert!(chain.is_valid());
+    }
+
+    #[test]
+    fn test_validate_rejects_block_claiming_unmet_difficulty() {
+        let mut chain = Blockchain::new();
+        chain.add_block(vec![dummy_tx([1; 32], [2; 32], 10)]);
+        // Завышаем заявленную сложность и пересчитываем хеш без майнинга
+        chain.blocks[1].difficulty = 64;
+        chain.blocks[1].hash = chain.blocks[1].calculate_hash();
+        assert!(matches!(
+            chain.validate(),
+            Err(ChainError::InsufficientWork { index: 1 })
+        ));
+    }
+
+    /// Добавляет в цепочку намайненный блок с заданными временем и сложностью.
+    fn push_block_at(chain: &mut Blockchain, timestamp: u64, difficulty: u32) {
+        let previous = chain.blocks.last().unwrap();
+        let mut block = Block::new(previous.index + 1, timestamp, vec![], previous.hash);
+        block.mine(difficulty as usize).unwrap();
+        chain.blocks.push(block);
+    }
+
+    fn retarget_params() -> MiningParams {
+        MiningParams {
+            window: 3,
+            target_block_secs: 10,
+            initial_difficulty: 2,
+        }
+    }
+
+    #[test]
+    fn test_next_difficulty_rises_for_fast_blocks() {
+        let mut chain = Blockchain::with_mining(retarget_params());
+        let start = chain.blocks[0].timestamp;
+        for i in 1..=3 {
+            push_block_at(&mut chain, start + i * 1_000_000_000, 2);
+        }
+        assert_eq!(chain.next_difficulty(), 3);
+    }
+
+    #[test]
+    fn test_next_difficulty_is_capped_at_max() {
+        let mut chain = Blockchain::with_mining(retarget_params());
+        let start = chain.blocks[0].timestamp;
+        for i in 1..=3 {
+            push_block_at(&mut chain, start + i * 1_000_000_000, 0);
+        }
+        chain.blocks[3].difficulty = MAX_DIFFICULTY;
+        assert_eq!(chain.next_difficulty(), MAX_DIFFICULTY);
+        chain.blocks[3].difficulty = u32::MAX;
+        assert_eq!(chain.next_difficulty(), MAX_DIFFICULTY);
+    }
+
+    #[test]
+    fn test_next_difficulty_falls_for_slow_blocks() {
+        let mut chain = Blockchain::with_mining(retarget_params());
+        let start = chain.blocks[0].timestamp;
+        for i in 1..=3 {
+            push_block_at(&mut chain, start + i * 100_000_000_000, 2);
+        }
+        assert_eq!(chain.next_difficulty(), 1);
+    }
+
+    #[test]
+    fn test_next_difficulty_is_initial_without_history() {
+        let chain = Blockchain::with_mining(retarget_params());
+        assert_eq!(chain.next_difficulty(), 2);
+    }
+
+    #[test]
+    fn test_validate_rejects_cheating_difficulty() {
+        let mut chain = Blockchain::with_mining(retarget_params());
+        chain.mine_block(vec![dummy_tx([1; 32], [2; 32], 1)]);
+        assert!(chain.is_valid());
+        chain.add_block_pow(vec![dummy_tx([3; 32], [4; 32], 1)], 1);
+        assert!(matches!(
+            chain.validate(),
+            Err(ChainError::DifficultyMismatch {
+                index: 2,
+                expected: 2,
+                actual: 1
+            })
+        ));
+    }
+
+    #[test]
+    fn test_iter_yields_blocks_in_order() {
+        let mut chain = Blockchain::new();
+        chain.add_block(vec![dummy_tx([1; 32], [2; 32], 1)]);
+        chain.add_block(vec![dummy_tx([3; 32], [4; 32], 1)]);
+        let indices: Vec<u64> = chain.iter().map(|block| block.index).collect();
+        assert_eq!(indices, vec![0, 1, 2]);
+        assert_eq!(chain.len(), 3);
+        assert!(!chain.is_empty());
+    }
+
+    #[test]
+    fn test_fixed_genesis_yields_identical_hashes() {
+        let first = Blockchain::new_with_timestamp(1_700_000_000);
+        let second = Blockchain::new_with_timestamp(1_700_000_000);
+        assert_eq!(first.blocks[0].hash, second.blocks[0].hash);
+
+        let custom = Blockchain::with_genesis(first.blocks[0].clone()).unwrap();
+        assert_eq!(custom.blocks[0].hash, second.blocks[0].hash);
+        assert!(custom.is_valid());
+    }
+
+    #[test]
+    fn test_with_genesis_rejects_invalid_block() {
+        let mut genesis = Blockchain::new_with_timestamp(1).blocks[0].clone();
+        genesis.index = 1;
+        assert!(matches!(
+            Blockchain::with_genesis(genesis),
+            Err(ChainError::InvalidGenesis)
+        ));
+
+        let mut genesis = Blockchain::new_with_timestamp(1).blocks[0].clone();
+        genesis.hash = [1u8; 32];
+        assert!(matches!(
+            Blockchain::with_genesis(genesis),
+            Err(ChainError::InvalidGenesis)
+        ));
+    }
+
+    #[test]
+    fn test_try_add_block_rejects_duplicate_transaction() {
+        let mut chain = Blockchain::new();
+        let tx = dummy_tx([1; 32], [2; 32], 5);
+        let result = chain.try_add_block(vec![tx.clone(), dummy_tx([3; 32], [4; 32], 1), tx]);
+        assert!(matches!(
+            result,
+            Err(ChainError::DuplicateTransaction { position: 2 })
+        ));
+        assert_eq!(chain.len(), 1);
+    }
+
+    #[test]
+    fn test_try_add_block_rejects_too_many_transactions() {
+        let mut chain = Blockchain::new();
+        let txs = (0..=MAX_TRANSACTIONS_PER_BLOCK as u64)
+            .map(|i| dummy_tx([1; 32], [2; 32], i + 1))
+            .collect();
+        assert!(matches!(
+            chain.try_add_block(txs),
+            Err(ChainError::TooManyTransactions { count: 11, max: 10 })
+        ));
+    }
+
+    #[test]
+    fn test_merkle_root_changes_with_single_transaction() {
+        let txs: Vec<Transaction> = (1..=5).map(|i| dummy_tx([i; 32], [9; 32], 10)).collect();
+        let root = merkle_root(&txs);
+        let mut changed = txs.clone();
+        changed[4].amount = 11;
+        assert_ne!(root, merkle_root(&changed));
+        assert_eq!(merkle_root(&[]), [0u8; 32]);
+
+        let block = Block::new(1, 1, txs, [0u8; 32]);
+        assert_eq!(block.merkle_root, root);
+        assert!(block.verify_merkle_root());
+
+        let mut chain = Blockchain::new();
+        chain.add_block(vec![dummy_tx([1; 32], [2; 32], 1)]);
+        chain.blocks[1].transactions[0].amount = 2;
+        assert!(matches!(
+            chain.validate(),
+            Err(ChainError::MerkleRootMismatch { index: 1 })
+        ));
+    }
+
+    #[test]
+    fn test_merkle_root_duplicates_last_leaf_on_odd_count() {
+        let txs: Vec<Transaction> = (1..=3).map(|i| dummy_tx([i; 32], [9; 32], 10)).collect();
+        let leaves: Vec<[u8; 32]> = txs.iter().map(hash_transaction).collect();
+        let expected = hash_pair(
+            &hash_pair(&leaves[0], &leaves[1]),
+            &hash_pair(&leaves[2], &leaves[2]),
+        );
+        assert_eq!(merkle_root(&txs), expected);
+    }
+
+    #[test]
+    fn test_merkle_proofs_verify_for_every_transaction() {
+        for count in 1..=7u8 {
+            let txs: Vec<Transaction> = (1..=count)
+                .map(|i| dummy_tx([i; 32], [9; 32], 10))
+                .collect();
+            let block = Block::new(1, 1, txs, [0u8; 32]);
+            for (i, tx) in block.transactions.iter().enumerate() {
+                let proof = block.merkle_proof(i).unwrap();
+                assert!(verify_merkle_proof(tx, &proof, block.merkle_root, i));
+            }
+            assert!(block.merkle_proof(count as usize).is_none());
+        }
+    }
+
+    #[test]
+    fn test_tampered_merkle_proof_fails() {
+        let txs: Vec<Transaction> = (1..=4).map(|i| dummy_tx([i; 32], [9; 32], 10)).collect();
+        let block = Block::new(1, 1, txs, [0u8; 32]);
+        let tx = &block.transactions[1];
+        let mut proof = block.merkle_proof(1).unwrap();
+        assert!(!verify_merkle_proof(tx, &proof, block.merkle_root, 2));
+        assert!(!verify_merkle_proof(
+            &dummy_tx([1; 32], [9; 32], 99),
+            &proof,
+            block.merkle_root,
+            1
+        ));
+        proof[0][0] ^= 1;
+        assert!(!verify_merkle_proof(tx, &proof, block.merkle_root, 1));
+    }
+
+    #[test]
+    fn test_try_add_block_rejects_zero_amount() {
+        let mut chain = Blockchain::new();
+        let result = chain.try_add_block(vec![
+            dummy_tx([1; 32], [2; 32], 5),
+            dummy_tx([3; 32], [4; 32], 0),
+        ]);
+        assert!(matches!(
+            result,
+            Err(ChainError::ZeroAmount { position: 1 })
+        ));
+        assert_eq!(chain.len(), 1);
+    }
+
+    #[test]
+    fn test_try_add_block_rejects_amount_overflow() {
+        let mut chain = Blockchain::new();
+        let txs = vec![
+            dummy_tx([1; 32], [2; 32], u64::MAX),
+            dummy_tx([3; 32], [4; 32], 1),
+        ];
+        assert_eq!(
+            Block::new(1, 1, txs.clone(), [0u8; 32]).total_amount(),
+            None
+        );
+        assert!(matches!(
+            chain.try_add_block(txs),
+            Err(ChainError::AmountOverflow)
+        ));
+
+        chain.add_block(vec![
+            dummy_tx([1; 32], [2; 32], 40),
+            dummy_tx([3; 32], [4; 32], 2),
+        ]);
+        assert_eq!(chain.blocks[1].total_amount(), Some(42));
+    }
+
+    #[test]
+    fn test_balances_replay_transactions() {
+        let mut chain = Blockchain::new();
+        chain.add_block(vec![
+            dummy_tx([1; 32], [2; 32], 100),
+            dummy_tx([1; 32], [3; 32], 20).with_nonce(1),
+        ]);
+        chain.add_block(vec![dummy_tx([2; 32], [3; 32], 30)]);
+
+        assert_eq!(chain.balance_of(&[1; 32]), -120);
+        assert_eq!(chain.balance_of(&[2; 32]), 70);
+        assert_eq!(chain.balance_of(&[3; 32]), 50);
+        assert_eq!(chain.balance_of(&[4; 32]), 0);
+        assert_eq!(chain.balances().len(), 3);
+    }
+
+    #[test]
+    fn test_overdraft_protection_rejects_overdrawing_transfer() {
+        let mut chain = Blockchain::new();
+        // Без защиты адрес [1; 32] может уйти в минус и пополнить [2; 32]
+        chain.add_block(vec![dummy_tx([1; 32], [2; 32], 100)]);
+        let mut chain = chain.with_overdraft_protection(true);
+
+        chain
+            .try_add_block(vec![dummy_tx([2; 32], [3; 32], 60)])
+            .unwrap();
+        let result = chain.try_add_block(vec![
+            dummy_tx([2; 32], [3; 32], 30).with_nonce(1),
+            dummy_tx([2; 32], [4; 32], 20).with_nonce(2),
+        ]);
+        assert!(matches!(
+            result,
+            Err(ChainError::InsufficientFunds {
+                addr,
+                needed: 20,
+                available: 10
+            }) if addr == [2; 32]
+        ));
+        assert_eq!(chain.len(), 3);
+        assert_eq!(chain.balance_of(&[2; 32]), 40);
+
+        // Сумма больше `i64::MAX` не должна становиться отрицательной.
+        let result =
+            chain.try_add_block(vec![dummy_tx([2; 32], [3; 32], u64::MAX - 1).with_nonce(1)]);
+        assert!(matches!(
+            result,
+            Err(ChainError::InsufficientFunds {
+                needed,
+                available: 40,
+                ..
+            }) if needed == u64::MAX - 1
+        ));
+        let result = chain.try_add_block(vec![dummy_tx([2; 32], [3; 32], 1 << 63).with_nonce(1)]);
+        assert!(matches!(result, Err(ChainError::InsufficientFunds { .. })));
+        assert_eq!(chain.len(), 3);
+    }
+
+    #[test]
+    fn test_signed_transaction_verifies() {
+        let key = SigningKey::from_bytes(&[7u8; 32]);
+        let tx = signed_tx(&key, [2; 32], 10);
+        assert!(tx.verify());
+        assert!(!dummy_tx(key.verifying_key().to_bytes(), [2; 32], 10).verify());
+    }
+
+    #[test]
+    fn test_tampered_amount_fails_verification() {
+        let key = SigningKey::from_bytes(&[7u8; 32]);
+        let mut tx = signed_tx(&key, [2; 32], 10);
+        tx.amount = 1000;
+        assert!(!tx.verify());
+    }
+
+    #[test]
+    fn test_signature_verification_rejects_unsigned_block() {
+        let key = SigningKey::from_bytes(&[7u8; 32]);
+        let mut chain = Blockchain::new().with_signature_verification(true);
+        chain
+            .try_add_block(vec![signed_tx(&key, [2; 32], 10)])
+            .unwrap();
+
+        let mut forged = signed_tx(&key, [3; 32], 10);
+        forged.to = [4; 32];
+        assert!(matches!(
+            chain.try_add_block(vec![forged.clone()]),
+            Err(ChainError::InvalidSignature {
+                index: 2,
+                position: 0
+            })
+        ));
+
+        chain.blocks[1].transactions[0] = forged;
+        chain.blocks[1].merkle_root = merkle_root(&chain.blocks[1].transactions);
+        chain.blocks[1].address_filter = address_filter(&chain.blocks[1].transactions);
+        chain.blocks[1].hash = chain.blocks[1].calculate_hash();
+        assert!(matches!(
+            chain.validate(),
+            Err(ChainError::InvalidSignature {
+                index: 1,
+                position: 0
+            })
+        ));
+    }
+
+    fn multisig_2_of_3() -> (Vec<SigningKey>, MultiSig) {
+        let keys: Vec<_> = (1..=3u8)
+            .map(|i| SigningKey::from_bytes(&[i; 32]))
+            .collect();
+        let public = keys.iter().map(|k| k.verifying_key().to_bytes()).collect();
+        (keys, MultiSig::new(2, public))
+    }
+
+    #[test]
+    fn test_multisig_requires_m_signatures() {
+        let (keys, multisig) = multisig_2_of_3();
+        let mut tx = Transaction::new_multisig(multisig.clone(), [9; 32], 10);
+        assert_eq!(tx.from, multisig.address());
+        assert!(!tx.verify());
+
+        assert!(tx.cosign(&keys[0]));
+        assert!(!tx.verify());
+        assert!(tx.cosign(&keys[0]));
+        assert!(!tx.verify());
+        assert!(tx.cosign(&keys[2]));
+        assert!(tx.verify());
+        assert!(!tx.cosign(&SigningKey::from_bytes(&[4; 32])));
+
+        let mut stolen = tx.clone();
+        stolen.from = [5; 32];
+        assert!(!stolen.verify());
+        let mut tampered = tx;
+        tampered.amount = 1000;
+        assert!(!tampered.verify());
+    }
+
+    #[test]
+    fn test_block_validation_enforces_multisig() {
+        let (keys, multisig) = multisig_2_of_3();
+        let mut chain = Blockchain::new_with_timestamp(1);
+        let mut tx = Transaction::new_multisig(multisig, [9; 32], 10);
+        tx.cosign(&keys[1]);
+        assert!(matches!(
+            chain.try_add_block(vec![tx.clone()]),
+            Err(ChainError::InvalidSignature {
+                index: 1,
+                position: 0
+            })
+        ));
+        tx.cosign(&keys[2]);
+        chain.try_add_block(vec![tx]).unwrap();
+        assert!(chain.is_valid());
+
+        chain.blocks[1].transactions[0]
+            .multisig
+            .as_mut()
+            .unwrap()
+            .signatures
+            .pop();
+        chain.blocks[1].merkle_root = merkle_root(&chain.blocks[1].transactions);
+        chain.blocks[1].hash = chain.blocks[1].calculate_hash();
+        assert!(matches!(
+            chain.validate(),
+            Err(ChainError::InvalidSignature {
+                index: 1,
+                position: 0
+            })
+        ));
+    }
+
+    #[test]
+    fn test_replayed_transaction_is_rejected_by_nonce() {
+        let key = SigningKey::from_bytes(&[7u8; 32]);
+        let sender = key.verifying_key().to_bytes();
+        let mut chain = Blockchain::new().with_signature_verification(true);
+        let tx = signed_tx(&key, [2; 32], 10);
+        chain.try_add_block(vec![tx.clone()]).unwrap();
+
+        assert!(matches!(
+            chain.try_add_block(vec![tx]),
+            Err(ChainError::BadNonce {
+                expected: 1,
+                got: 0,
+                ..
+            })
+        ));
+
+        assert_eq!(chain.next_nonce(&sender), Some(1));
+        let mut next = Transaction::new(sender, [2; 32], 10).with_nonce(1);
+        next.sign(&key);
+        chain.try_add_block(vec![next]).unwrap();
+        assert_eq!(chain.len(), 3);
+    }
+
+    #[test]
+    fn test_streaming_hash_matches_fixture() {
+        let tx = dummy_tx([1; 32], [2; 32], 10).with_nonce(3);
+        let block = Block::new(1, 1_700_000_000, vec![tx], [2u8; 32]);
+        assert_eq!(
+            hex::encode(block.merkle_root),
+            "d476870a097f6be16a972d1c965081fa98288fef5cc49c9e0e4a909fc3912bf8"
+        );
+        assert_eq!(
+            hex::encode(block.hash),
+            "ac4dd4156e0e3071de8ea7af2749277c919b24ff1f94f112afc198cca0d13039"
+        );
+    }
+
+    #[test]
+    fn test_header_bytes_are_big_endian() {
+        let block = Block::new(1, 1_700_000_000, vec![], [2u8; 32]);
+        let bytes = block.header_bytes();
+        assert_eq!(bytes[..8], 1u64.to_be_bytes());
+        assert_eq!(bytes[8..16], 1_700_000_000u64.to_be_bytes());
+        assert_eq!(bytes[crate::BLOCK_HEADER_LEN - 4..], 0u32.to_be_bytes());
+        // Хеш не зависит от того, как bincode кодирует те же поля.
+        let bincode_layout = bincode::serialize(&(
+            block.index,
+            block.timestamp,
+            block.merkle_root,
+            block.previous_hash,
+            block.nonce,
+            block.difficulty,
+        ))
+        .unwrap();
+        assert_ne!(bytes.as_slice(), bincode_layout.as_slice());
+        assert_eq!(
+            hex::encode(block.hash),
+            "8c7a9345958ac12e636ffca53ad359042c0b07f81d64b40e4c1ad920af98a1ed"
+        );
+    }
+
+    #[test]
+    fn test_block_serialization_roundtrip() {
+        let block = Block::new(
+            1,
+            1700000000,
+            vec![dummy_tx([1; 32], [2; 32], 10)],
+            [2u8; 32],
+        );
+
+        let serialized = serialize_block(&block).unwrap();
+        let deserialized: Block = deserialize_block(&serialized).unwrap();
+        assert_eq!(block, deserialized);
+        assert_eq!(deserialized.hash, deserialized.calculate_hash());
+    }
+
+    #[test]
+    fn test_blockchain_serialization_roundtrip() {
+        let mut chain = Blockchain::new();
+        chain.add_block(vec![dummy_tx([5; 32], [6; 32], 42)]);
+        let serialized = serialize_blockchain(&chain).unwrap();
+        let deserialized: Blockchain = deserialize_blockchain(&serialized).unwrap();
+        assert_eq!(chain, deserialized);
+        assert!(deserialized.is_valid());
+    }
+
+    #[test]
+    fn test_save_and_load_file_roundtrip() {
+        let mut chain = Blockchain::new();
+        chain.add_block(vec![dummy_tx([1; 32], [2; 32], 7)]);
+        let file = tempfile::NamedTempFile::new().unwrap();
+        chain.save_to_file(file.path()).unwrap();
+
+        let loaded = Blockchain::load_from_file(file.path()).unwrap();
+        assert_eq!(chain, loaded);
+    }
+
+    #[test]
+    fn test_load_rejects_invalid_chain_on_disk() {
+        let mut chain = Blockchain::new();
+        chain.add_block(vec![dummy_tx([1; 32], [2; 32], 7)]);
+        chain.blocks[1].transactions[0].amount = 8;
+        let file = tempfile::NamedTempFile::new().unwrap();
+        chain.save_to_file(file.path()).unwrap();
+
+        assert!(matches!(
+            Blockchain::load_from_file(file.path()),
+            Err(ChainError::MerkleRootMismatch { index: 1 })
+        ));
+        assert!(matches!(
+            Blockchain::load_from_file(file.path().with_extension("missing")),
+            Err(ChainError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn test_consensus_approves_block_with_majority() {
+        let peers = vec![Peer::new(1), Peer::new(2), Peer::new(3)];
+        let consensus = FixedPeerConsensus::new(peers);
+        let mut chain = Blockchain::new();
+        let approved = consensus.propose_block(vec![dummy_tx([1; 32], [2; 32], 100)], &mut chain);
+        assert!(approved);
+    }
+
+    #[test]
+    fn test_consensus_rejects_block_without_peers() {
+        let consensus = FixedPeerConsensus::new(vec![]);
+        let mut chain = Blockchain::new();
+        let approved = consensus.propose_block(vec![dummy_tx([1; 32], [2; 32], 1)], &mut chain);
+        assert!(!approved);
+    }
+
+    #[test]
+    fn test_single_peer_decides_alone() {
+        let mut chain = Blockchain::new();
+        let honest = FixedPeerConsensus::new(vec![Peer::new(1)]);
+        assert!(honest.propose_block(vec![dummy_tx([1; 32], [2; 32], 1)], &mut chain));
+        let dishonest = FixedPeerConsensus::new(vec![Peer::dishonest(1)]);
+        assert!(!dishonest.propose_block(vec![dummy_tx([1; 32], [2; 32], 2)], &mut chain));
+        assert_eq!(chain.len(), 2);
+    }
+
+    #[test]
+    fn test_two_peers_need_both_votes() {
+        let mut chain = Blockchain::new();
+        let split = FixedPeerConsensus::new(vec![Peer::new(1), Peer::dishonest(2)]);
+        assert!(!split.propose_block(vec![dummy_tx([1; 32], [2; 32], 1)], &mut chain));
+        let both = FixedPeerConsensus::new(vec![Peer::new(1), Peer::new(2)]);
+        assert!(both.propose_block(vec![dummy_tx([1; 32], [2; 32], 1)], &mut chain));
+        assert_eq!(chain.len(), 2);
+    }
+
+    #[test]
+    fn test_required_approvals_is_strict_majority() {
+        let required =
+            |n: u32| FixedPeerConsensus::new((1..=n).map(Peer::new).collect()).required_approvals();
+        assert_eq!(required(1), 1);
+        assert_eq!(required(2), 2);
+        assert_eq!(required(3), 2);
+        // Ничья 2 из 4 не является большинством
+        assert_eq!(required(4), 3);
+        assert_eq!(required(5), 3);
+    }
+
+    struct RejectAll;
+
+    impl Consensus for RejectAll {
+        fn approve(&self, _transactions: &[Transaction], _chain: &Blockchain) -> bool {
+            false
+        }
+    }
+
+    #[test]
+    fn test_custom_consensus_can_reject_blocks() {
+        let mut chain = Blockchain::new();
+        let added = RejectAll.propose_block(vec![dummy_tx([1; 32], [2; 32], 1)], &mut chain);
+        assert!(!added);
+        assert_eq!(chain.len(), 1);
+    }
+
+    #[test]
+    fn test_consensus_rejects_with_dishonest_majority() {
+        let peers = vec![
+            Peer::new(1),
+            Peer::new(2),
+            Peer::dishonest(3),
+            Peer::dishonest(4),
+            Peer::dishonest(5),
+        ];
+        let consensus = FixedPeerConsensus::new(peers);
+        let mut chain = Blockchain::new();
+        assert!(!consensus.propose_block(vec![dummy_tx([1; 32], [2; 32], 1)], &mut chain));
+        assert_eq!(chain.len(), 1);
+    }
+
+    #[test]
+    fn test_consensus_approves_with_single_dishonest_peer() {
+        let mut peers: Vec<Peer> = (1..=4).map(Peer::new).collect();
+        peers.push(Peer::dishonest(5));
+        let consensus = FixedPeerConsensus::new(peers);
+        let mut chain = Blockchain::new();
+        assert!(consensus.propose_block(vec![dummy_tx([1; 32], [2; 32], 1)], &mut chain));
+        assert_eq!(chain.len(), 2);
+    }
+
+    #[test]
+    fn test_bft_tolerates_exactly_f_faults() {
+        for f in 1..=3 {
+            let mut consensus = BftConsensus::tolerating(f);
+            assert_eq!(consensus.peers.len(), 3 * f + 1);
+            assert_eq!(consensus.fault_tolerance(), f);
+            assert_eq!(consensus.required_approvals(), 2 * f + 1);
+
+            let chain = Blockchain::new();
+            let txs = vec![dummy_tx([1; 32], [2; 32], 1)];
+            for peer in consensus.peers.iter_mut().take(f) {
+                peer.is_honest = false;
+            }
+            assert!(consensus.approve(&txs, &chain));
+            consensus.peers[f].is_honest = false;
+            assert!(!consensus.approve(&txs, &chain));
+        }
+    }
+
+    #[test]
+    fn test_bft_quorum_for_any_peer_count() {
+        let quorum = |n: u32| {
+            BftConsensus {
+                peers: (1..=n).map(Peer::new).collect(),
+            }
+            .required_approvals()
+        };
+        assert_eq!(quorum(1), 1);
+        assert_eq!(quorum(4), 3);
+        assert_eq!(quorum(5), 4);
+        assert_eq!(quorum(6), 5);
+        assert_eq!(quorum(7), 5);
+
+        // 3 из 6 — ничья, а не кворум
+        let mut consensus = BftConsensus {
+            peers: (1..=6).map(Peer::new).collect(),
+        };
+        for peer in consensus.peers.iter_mut().take(3) {
+            peer.is_honest = false;
+        }
+        let txs = vec![dummy_tx([1; 32], [2; 32], 1)];
+        assert!(!consensus.approve(&txs, &Blockchain::new()));
+    }
+
+    #[test]
+    fn test_stake_majority_outweighs_peer_count() {
+        // Один честный пир с большой долей против трёх нечестных с малыми
+        let consensus = StakeConsensus::new(vec![
+            (Peer::new(1), 100),
+            (Peer::dishonest(2), 30),
+            (Peer::dishonest(3), 30),
+            (Peer::dishonest(4), 30),
+        ]);
+        let mut chain = Blockchain::new();
+        assert!(consensus.propose_block(vec![dummy_tx([1; 32], [2; 32], 1)], &mut chain));
+
+        let consensus = StakeConsensus::new(vec![(Peer::new(1), 50), (Peer::dishonest(2), 50)]);
+        assert!(!consensus.approve(&[], &chain));
+    }
+
+    #[test]
+    fn test_stake_consensus_rejects_zero_total_stake() {
+        let chain = Blockchain::new();
+        assert!(!StakeConsensus::new(vec![]).approve(&[], &chain));
+        let consensus = StakeConsensus::new(vec![(Peer::new(1), 0), (Peer::new(2), 0)]);
+        assert!(!consensus.approve(&[], &chain));
+    }
+
+    #[test]
+    fn test_get_block_by_hash() {
+        let mut chain = Blockchain::new();
+        for i in 1..=4 {
+            chain.add_block(vec![dummy_tx([i; 32], [9; 32], 1)]);
+        }
+        for block in chain.iter() {
+            let found = chain.get_block_by_hash(&block.hash).unwrap();
+            assert_eq!(found.index, block.index);
+        }
+        assert!(chain.get_block_by_hash(&[0xAB; 32]).is_none());
+
+        // Без индекса (после десериализации) работает линейный поиск
+        let restored = deserialize_blockchain(&serialize_blockchain(&chain).unwrap()).unwrap();
+        let tip = chain.blocks[4].hash;
+        assert_eq!(restored.get_block_by_hash(&tip).unwrap().index, 4);
+    }
+
+    #[test]
+    fn test_find_transaction_locates_block_and_position() {
+        let mut chain = Blockchain::new();
+        chain.add_block(vec![dummy_tx([1; 32], [2; 32], 1)]);
+        let target = dummy_tx([3; 32], [4; 32], 2);
+        chain.add_block(vec![dummy_tx([5; 32], [6; 32], 3), target.clone()]);
+
+        assert_eq!(chain.find_transaction(&target), Some((2, 1)));
+        assert_eq!(chain.find_transaction(&dummy_tx([7; 32], [8; 32], 1)), None);
+    }
+
+    #[test]
+    fn test_transactions_for_address_matches_both_directions() {
+        let mut chain = Blockchain::new();
+        chain.add_block(vec![dummy_tx([1; 32], [2; 32], 1)]);
+        chain.add_block(vec![dummy_tx([3; 32], [4; 32], 2)]);
+        chain.add_block(vec![dummy_tx([2; 32], [5; 32], 3)]);
+
+        let found = chain.transactions_for_address(&[2; 32]);
+        let summary: Vec<(u64, u64)> = found.iter().map(|(i, tx)| (*i, tx.amount)).collect();
+        assert_eq!(summary, vec![(1, 1), (3, 3)]);
+        assert!(chain.transactions_for_address(&[9; 32]).is_empty());
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_parallel_validation_matches_serial() {
+        let mut chain = Blockchain::new();
+        for i in 0..1000u64 {
+            let from = (i % 255) as u8 + 1;
+            let tx = dummy_tx([from; 32], [0; 32], i + 1).with_nonce(i);
+            chain.add_block(vec![tx]);
+        }
+        assert!(chain.is_valid());
+        assert_eq!(chain.is_valid_parallel(), chain.is_valid());
+
+        chain.blocks[500].transactions[0].amount += 1;
+        assert!(!chain.is_valid());
+        assert_eq!(chain.is_valid_parallel(), chain.is_valid());
+
+        chain.blocks[500].transactions[0].amount -= 1;
+        chain.blocks[700].previous_hash = [1; 32];
+        chain.blocks[700].hash = chain.blocks[700].calculate_hash();
+        assert_eq!(chain.is_valid_parallel(), chain.is_valid());
+
+        // Завышенная награда с пересчитанными хешами.
+        let mut rewarded = Blockchain::with_config(ChainConfig {
+            block_reward: 50,
+            miner: Some([5; 32]),
+            ..ChainConfig::default()
+        });
+        for i in 0..3u64 {
+            rewarded.add_block(vec![dummy_tx([1; 32], [2; 32], 1).with_nonce(i)]);
+        }
+        assert!(rewarded.is_valid_parallel());
+        let coinbase = rewarded.blocks[2]
+            .transactions
+            .iter_mut()
+            .find(|tx| tx.is_coinbase())
+            .unwrap();
+        coinbase.amount += 1_000;
+        rewarded.rebuild_hashes();
+        assert!(matches!(
+            rewarded.validate(),
+            Err(ChainError::InvalidReward { .. })
+        ));
+        assert!(!rewarded.is_valid_parallel());
+    }
+
+    #[test]
+    fn test_incremental_validation_matches_full_for_new_blocks() {
+        let mut chain = Blockchain::new();
+        chain.validate_incremental().unwrap();
+        for i in 1..=3 {
+            chain
+                .add_block_validated(vec![dummy_tx([i; 32], [9; 32], 1)])
+                .unwrap();
+        }
+        assert_eq!(chain.last_validated_len, 4);
+        assert!(chain.validate().is_ok());
+
+        // Блок, добавленный в обход проверок, обнаруживается обоими способами
+        let tip = chain.blocks.last().unwrap();
+        let bogus = Block::new(tip.index + 1, tip.timestamp + 1, vec![], [5; 32]);
+        chain.blocks.push(bogus);
+        assert!(matches!(
+            chain.validate_incremental(),
+            Err(ChainError::BrokenLink { index: 4 })
+        ));
+        assert!(matches!(
+            chain.validate(),
+            Err(ChainError::BrokenLink { index: 4 })
+        ));
+    }
+
+    #[test]
+    fn test_incremental_validation_skips_already_checked_blocks() {
+        let mut chain = Blockchain::new();
+        chain.add_block(vec![dummy_tx([1; 32], [2; 32], 1)]);
+        chain.validate_incremental().unwrap();
+
+        // Подмена старого блока видна только полной проверке
+        chain.blocks[1].transactions[0].amount = 2;
+        assert!(chain.validate_incremental().is_ok());
+        assert!(chain.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_replays_transaction_rules() {
+        let mut chain = Blockchain::new_deterministic();
+        chain.add_block(vec![dummy_tx([1; 32], [2; 32], 5)]);
+        chain.validate_incremental().unwrap();
+
+        // Повтор транзакции в блоке, добавленном в обход проверок
+        let tip = chain.blocks.last().unwrap();
+        let mut replay = Block::new(
+            tip.index + 1,
+            tip.timestamp + 1,
+            tip.transactions.clone(),
+            tip.hash,
+        );
+        replay.mine(tip.difficulty as usize).unwrap();
+        chain.blocks.push(replay);
+        assert!(matches!(chain.validate(), Err(ChainError::BadNonce { .. })));
+        assert!(!chain.is_valid());
+        assert!(matches!(
+            chain.validate_incremental(),
+            Err(ChainError::BadNonce { .. })
+        ));
+        assert_eq!(chain.last_validated_len, 2);
+
+        // Перерасход проверяется только при `overdraft_protection`
+        chain.blocks.pop();
+        chain.config.overdraft_protection = true;
+        assert!(matches!(
+            chain.validate(),
+            Err(ChainError::InsufficientFunds { .. })
+        ));
+        chain.config.overdraft_protection = false;
+        assert!(chain.validate().is_ok());
+    }
+
+    #[test]
+    fn test_stats_match_manual_computation() {
+        let mut chain = Blockchain::new();
+        chain.add_block(vec![
+            dummy_tx([1; 32], [2; 32], 10),
+            dummy_tx([3; 32], [4; 32], 20),
+        ]);
+        chain.add_block(vec![dummy_tx([5; 32], [6; 32], 30)]);
+
+        let sizes: Vec<usize> = chain
+            .iter()
+            .map(|b| serialize_block(b).unwrap().len())
+            .collect();
+        let stats = chain.stats();
+        assert_eq!(
+            stats,
+            ChainStats {
+                block_count: 3,
+                transaction_count: 3,
+                total_amount: 60,
+                average_block_size_bytes: sizes.iter().sum::<usize>() / 3,
+                last_hash: Some(chain.blocks[2].hash),
+                is_valid: true,
+            }
+        );
+    }
+
+    /// Строит цепочку с общим генезисом и `count` блоками.
+    fn chain_with_blocks(genesis_ts: u64, count: u8, seed: u8) -> Blockchain {
+        let mut chain = Blockchain::new_with_timestamp(genesis_ts);
+        for i in 0..count {
+            chain.add_block(vec![
+                dummy_tx([seed; 32], [100 + i; 32], 1).with_nonce(i as u64),
+            ]);
+        }
+        chain
+    }
+
+    #[test]
+    fn test_try_replace_keeps_longer_current_chain() {
+        let mut current = chain_with_blocks(1, 3, 1);
+        let shorter = chain_with_blocks(1, 2, 2);
+        let tip = current.blocks[3].hash;
+        assert!(!current.try_replace(shorter).unwrap());
+        assert_eq!(current.blocks[3].hash, tip);
+    }
+
+    #[test]
+    fn test_try_replace_accepts_longer_valid_chain() {
+        let mut current = chain_with_blocks(1, 2, 1);
+        let longer = chain_with_blocks(1, 4, 2);
+        let tip = longer.blocks[4].hash;
+        assert!(current.try_replace(longer).unwrap());
+        assert_eq!(current.len(), 5);
+        assert_eq!(current.get_block_by_hash(&tip).unwrap().index, 4);
+        assert!(current.is_valid());
+    }
+
+    #[test]
+    fn test_try_replace_refreshes_derived_state() {
+        let mut current = chain_with_blocks(1, 2, 1);
+        let old_tip = current.blocks[2].hash;
+        let cached = current.balances();
+        assert_eq!(cached[&[1; 32]], -2);
+        let mut orphan = current.clone();
+        orphan.add_block(vec![dummy_tx([1; 32], [2; 32], 1).with_nonce(2)]);
+        current
+            .import_headers(orphan.headers()[3..].to_vec())
+            .unwrap();
+
+        let longer = chain_with_blocks(1, 4, 2);
+        assert!(current.try_replace(longer.clone()).unwrap());
+        let balances = current.balances();
+        assert_ne!(balances, cached);
+        assert_eq!(balances, longer.balances());
+        assert_eq!(balances.get(&[1; 32]), None);
+        assert_eq!(current.next_nonce(&[2; 32]), Some(4));
+        assert!(current.get_block_by_hash(&old_tip).is_none());
+        assert!(current.pending_headers().is_empty());
+    }
+
+    #[test]
+    fn test_try_replace_rejects_longer_invalid_chain() {
+        let mut current = chain_with_blocks(1, 2, 1);
+        let mut longer = chain_with_blocks(1, 4, 2);
+        longer.blocks[2].transactions[0].amount = 5;
+        assert!(matches!(
+            current.try_replace(longer),
+            Err(ChainError::MerkleRootMismatch { index: 2 })
+        ));
+        assert_eq!(current.len(), 3);
+    }
+
+    #[test]
+    fn test_total_work_prefers_higher_difficulty_over_length() {
+        let mut current = chain_with_blocks(1, 4, 1);
+        assert_eq!(current.total_work(), 5);
+
+        let mut heavier = Blockchain::new_with_timestamp(1);
+        heavier.add_block_pow(vec![dummy_tx([2; 32], [3; 32], 1)], 4);
+        heavier.add_block_pow(vec![dummy_tx([4; 32], [3; 32], 1)], 4);
+        assert_eq!(heavier.total_work(), 1 + 16 + 16);
+
+        assert!(current.try_replace(heavier).unwrap());
+        assert_eq!(current.len(), 3);
+    }
+
+    #[test]
+    fn test_try_replace_keeps_current_chain_on_equal_work() {
+        let mut current = chain_with_blocks(1, 2, 1);
+        let rival = chain_with_blocks(1, 2, 2);
+        let tip = current.blocks[2].hash;
+        assert_eq!(current.total_work(), rival.total_work());
+        assert!(!current.try_replace(rival).unwrap());
+        assert_eq!(current.blocks[2].hash, tip);
+    }
+
+    #[test]
+    fn test_verify_against_reports_each_failure() {
+        let previous = Block::new(4, 100, vec![], [3; 32]);
+        let txs = vec![dummy_tx([1; 32], [2; 32], 1)];
+        let good = Block::new(5, 101, txs.clone(), previous.hash);
+        assert!(good.verify_against(&previous).is_ok());
+
+        let wrong_index = Block::new(6, 101, txs.clone(), previous.hash);
+        assert!(matches!(
+            wrong_index.verify_against(&previous),
+            Err(ChainError::NonMonotonicIndex { index: 6 })
+        ));
+
+        let wrong_link = Block::new(5, 101, txs.clone(), [9; 32]);
+        assert!(matches!(
+            wrong_link.verify_against(&previous),
+            Err(ChainError::BrokenLink { index: 5 })
+        ));
+
+        let stale = Block::new(5, 100, txs.clone(), previous.hash);
+        assert!(matches!(
+            stale.verify_against(&previous),
+            Err(ChainError::TimestampNotIncreasing { index: 5 })
+        ));
+
+        let mut bad_hash = good.clone();
+        bad_hash.hash = [0; 32];
+        assert!(matches!(
+            bad_hash.verify_against(&previous),
+            Err(ChainError::HashMismatch { index: 5 })
+        ));
+
+        let mut bad_body = good.clone();
+        bad_body.transactions.clear();
+        assert!(matches!(
+            bad_body.verify_against(&previous),
+            Err(ChainError::MerkleRootMismatch { index: 5 })
+        ));
+    }
+
+    #[test]
+    fn test_config_limits_transactions_per_block() {
+        let mut chain = Blockchain::with_config(ChainConfig {
+            max_transactions_per_block: 2,
+            ..ChainConfig::default()
+        });
+        let txs: Vec<Transaction> = (1..=3).map(|i| dummy_tx([i; 32], [9; 32], 1)).collect();
+        assert!(matches!(
+            chain.try_add_block(txs.clone()),
+            Err(ChainError::TooManyTransactions { count: 3, max: 2 })
+        ));
+        chain.try_add_block(txs[..2].to_vec()).unwrap();
+        assert_eq!(chain.len(), 2);
+        assert_eq!(
+            Blockchain::new().config.max_transactions_per_block,
+            MAX_TRANSACTIONS_PER_BLOCK
+        );
+    }
+
+    #[test]
+    fn test_block_too_soon_is_rejected() {
+        let mut chain = Blockchain::with_config(ChainConfig {
+            min_block_interval: Some(3_600_000_000_000),
+            ..ChainConfig::default()
+        });
+        assert!(matches!(
+            chain.try_add_block(vec![dummy_tx([1; 32], [2; 32], 1)]),
+            Err(ChainError::BlockTooSoon { index: 1, .. })
+        ));
+        assert_eq!(chain.len(), 1);
+    }
+
+    #[test]
+    fn test_block_too_late_is_rejected() {
+        let mut chain = Blockchain::new_with_timestamp(0);
+        chain.config.max_block_interval = Some(1_000_000_000);
+        assert!(matches!(
+            chain.try_add_block(vec![dummy_tx([1; 32], [2; 32], 1)]),
+            Err(ChainError::BlockTooLate {
+                index: 1,
+                max: 1_000_000_000,
+                ..
+            })
+        ));
+
+        let mut relaxed = Blockchain::new_with_timestamp(0);
+        relaxed.config.max_block_interval = Some(u64::MAX);
+        relaxed
+            .try_add_block(vec![dummy_tx([1; 32], [2; 32], 1)])
+            .unwrap();
+    }
+
+    #[test]
+    fn test_current_timestamp_is_in_nanoseconds() {
+        let first = current_timestamp();
+        std::thread::sleep(std::time::Duration::from_millis(1));
+        let second = current_timestamp();
+        assert!(second > first);
+        // Не меньше миллисекунды, но заметно меньше минуты.
+        assert!(second - first >= 1_000_000);
+        assert!(second - first < 60 * NANOS_PER_SEC);
+        // После 2020-01-01 и до 2554 года в наносекундах.
+        assert!(first > 1_577_836_800 * NANOS_PER_SEC);
+    }
+
+    #[test]
+    fn test_many_blocks_in_tight_loop_do_not_panic() {
+        let mut chain = Blockchain::new();
+        for i in 1..=200u8 {
+            chain.add_block(vec![dummy_tx([i; 32], [0; 32], 1)]);
+        }
+        assert_eq!(chain.len(), 201);
+        assert!(chain.is_valid());
+    }
+
+    #[test]
+    fn test_create_block_clamps_timestamp_from_future_parent() {
+        let far_future = current_timestamp() + 3600 * NANOS_PER_SEC;
+        let mut chain = Blockchain::new_with_timestamp(far_future);
+        chain.add_block(vec![dummy_tx([1; 32], [2; 32], 1)]);
+        assert_eq!(chain.blocks[1].timestamp, far_future + 1);
+        assert!(chain.is_valid());
+    }
+
+    #[test]
+    fn test_equal_timestamps_are_a_validation_policy() {
+        let mut chain = Blockchain::new();
+        let ts = chain.blocks[0].timestamp;
+        push_block_at(&mut chain, ts, 0);
+        assert!(matches!(
+            chain.validate(),
+            Err(ChainError::TimestampNotIncreasing { index: 1 })
+        ));
+        chain.config.allow_equal_timestamps = true;
+        assert!(chain.validate().is_ok());
+    }
+
+    #[test]
+    fn test_chain_id_depends_on_genesis() {
+        let a = chain_with_blocks(1, 2, 1);
+        let b = chain_with_blocks(1, 3, 2);
+        let other = Blockchain::new_with_timestamp(2);
+        assert_eq!(a.genesis_hash(), b.genesis_hash());
+        assert_eq!(a.chain_id(), b.chain_id());
+        assert_eq!(a.chain_id(), hex::encode(a.blocks[0].hash));
+        assert_ne!(a.chain_id(), other.chain_id());
+    }
+
+    #[test]
+    fn test_try_replace_rejects_foreign_genesis() {
+        let mut current = chain_with_blocks(1, 2, 1);
+        let foreign = chain_with_blocks(2, 4, 2);
+        assert!(matches!(
+            current.try_replace(foreign),
+            Err(ChainError::GenesisMismatch { expected }) if expected == current.genesis_hash()
+        ));
+        assert_eq!(current.len(), 3);
+
+        let mut empty = Blockchain::from_blocks_unchecked(vec![]);
+        assert!(matches!(
+            empty.try_replace(current),
+            Err(ChainError::EmptyChain)
+        ));
+        assert!(empty.is_empty());
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn test_compressed_roundtrip_of_long_chain() {
+        let mut chain = Blockchain::new();
+        for i in 0..500u64 {
+            let tx = dummy_tx([1; 32], [(i % 254) as u8 + 2; 32], 1).with_nonce(i);
+            chain.add_block(vec![tx]);
+        }
+        let compressed = serialize_blockchain_compressed(&chain).unwrap();
+        let restored = deserialize_blockchain_compressed(&compressed).unwrap();
+        assert!(restored.is_valid());
+        assert_eq!(restored, chain);
+        assert!(compressed.len() < serialize_blockchain(&chain).unwrap().len());
+    }
+
+    #[test]
+    fn test_write_to_read_from_roundtrip() {
+        let chain = chain_with_blocks(1, 5, 1);
+        let mut cursor = std::io::Cursor::new(Vec::new());
+        chain.write_to(&mut cursor).unwrap();
+        assert_eq!(cursor.get_ref(), &serialize_blockchain(&chain).unwrap());
+
+        cursor.set_position(0);
+        let restored = Blockchain::read_from(cursor).unwrap();
+        assert!(restored.is_valid());
+        assert_eq!(restored, chain);
+    }
+
+    #[test]
+    fn test_block_log_replays_incremental_appends() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("blocks.log");
+        let mut chain = Blockchain::new();
+        let mut log = BlockLog::open(&path).unwrap();
+        log.append(&chain.blocks[0]).unwrap();
+        for i in 0..100u64 {
+            chain.add_block(vec![dummy_tx([1; 32], [2; 32], 1).with_nonce(i)]);
+            log.append(chain.blocks.last().unwrap()).unwrap();
+        }
+        drop(log);
+
+        let restored = BlockLog::open(&path).unwrap().load().unwrap();
+        assert_eq!(restored.len(), 101);
+        assert!(restored.is_valid());
+        assert_eq!(restored, chain);
+    }
+
+    #[test]
+    fn test_block_log_rejects_tampered_and_truncated_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("blocks.log");
+        let mut chain = chain_with_blocks(1, 2, 1);
+        chain.blocks[2].timestamp += 1;
+        let mut log = BlockLog::open(&path).unwrap();
+        for block in &chain.blocks {
+            log.append(block).unwrap();
+        }
+        assert!(matches!(
+            log.load(),
+            Err(ChainError::HashMismatch { index: 2 })
+        ));
+
+        let len = std::fs::metadata(&path).unwrap().len();
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_len(len - 1)
+            .unwrap();
+        assert!(matches!(log.load(), Err(ChainError::Io(_))));
+        assert!(matches!(
+            BlockLog::open(dir.path().join("empty.log")).unwrap().load(),
+            Err(ChainError::EmptyChain)
+        ));
+    }
+
+    #[test]
+    fn test_signing_bytes_match_bincode_layout() {
+        let tx = dummy_tx([1; 32], [2; 32], 10).with_nonce(7);
+        let tx = tx.with_fee(3);
+        let expected = bincode::serialize(&(tx.from, tx.to, tx.amount, tx.nonce, tx.fee)).unwrap();
+        assert_eq!(tx.signing_bytes(), expected);
+    }
+
+    #[test]
+    fn test_create_block_uses_injected_time() {
+        let genesis = create_genesis_block(100, &Sha256Hasher);
+        let block = create_block(vec![], &genesis, 250, false, &Sha256Hasher).unwrap();
+        assert_eq!(block.timestamp, 250);
+        let clamped = create_block(vec![], &genesis, 50, false, &Sha256Hasher).unwrap();
+        assert_eq!(clamped.timestamp, 101);
+        let equal = create_block(vec![], &genesis, 50, true, &Sha256Hasher).unwrap();
+        assert_eq!(equal.timestamp, 100);
+        assert!(block.verify_against(&genesis).is_ok());
+    }
+
+    /// Хеш-функция для тестов: SHA-256 с префиксом.
+    #[derive(Debug)]
+    struct PrefixedHasher;
+
+    impl Hasher for PrefixedHasher {
+        fn hash(&self, bytes: &[u8]) -> [u8; 32] {
+            use sha2::{Digest, Sha256};
+            Sha256::new()
+                .chain_update(b"stub")
+                .chain_update(bytes)
+                .finalize()
+                .into()
+        }
+    }
+
+    #[test]
+    fn test_custom_hasher_changes_block_hashes() {
+        let mut chain = Blockchain::with_config(ChainConfig {
+            hasher: Arc::new(PrefixedHasher),
+            ..ChainConfig::default()
+        });
+        chain.add_block(vec![dummy_tx([1; 32], [2; 32], 1)]);
+        chain.add_block_pow(vec![dummy_tx([3; 32], [2; 32], 1)], 1);
+        assert!(chain.is_valid());
+        for block in &chain.blocks {
+            assert_eq!(block.hash, PrefixedHasher.hash(&block.header_bytes()));
+            assert_ne!(block.hash, block.calculate_hash());
+        }
+
+        // Та же цепочка несовместима с SHA-256.
+        chain.config.hasher = Arc::new(Sha256Hasher);
+        assert!(matches!(chain.validate(), Err(ChainError::InvalidGenesis)));
+    }
+
+    #[test]
+    fn test_sha256_hasher_matches_default_hash() {
+        let block = Block::new(1, 1, vec![dummy_tx([1; 32], [2; 32], 1)], [3; 32]);
+        assert_eq!(
+            block.calculate_hash_with(&Sha256Hasher),
+            block.calculate_hash()
+        );
+        assert_eq!(block.hash, block.calculate_hash());
+    }
+
+    #[test]
+    fn test_blockchain_equality_compares_blocks() {
+        let a = chain_with_blocks(1, 3, 1);
+        let mut b = Blockchain::new().with_signature_verification(true);
+        b.blocks = a.blocks.clone();
+        assert_eq!(a, b);
+        b.blocks[2].nonce += 1;
+        assert_ne!(a, b);
+        assert_ne!(a, chain_with_blocks(1, 2, 1));
+    }
+
+    #[test]
+    fn test_display_lists_blocks_with_hashes() {
+        let chain = chain_with_blocks(1, 2, 1);
+        let text = format!("{}", chain);
+        for block in &chain.blocks {
+            assert!(text.contains(&format!("--- Block {} ---", block.index)));
+            assert!(text.contains(&format!("Hash: {}", hex::encode(block.hash))));
+        }
+        assert!(text.contains("(нет транзакций)"));
+        assert!(text.contains(&format!("{} → ", hex::encode([1u8; 32]))));
+
+        let block = chain.blocks[1].to_string();
+        assert!(block.starts_with("--- Block 1 ---\n"));
+        assert!(block.ends_with(&hex::encode(chain.blocks[0].hash)));
+    }
+
+    /// Логгер для тестов, сохраняющий записи в памяти.
+    struct CaptureLogger {
+        records: std::sync::Mutex<Vec<(log::Level, String)>>,
+    }
+
+    impl log::Log for CaptureLogger {
+        fn enabled(&self, _metadata: &log::Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record) {
+            let message = record.args().to_string();
+            self.records.lock().unwrap().push((record.level(), message));
+        }
+
+        fn flush(&self) {}
+    }
+
+    static LOGGER: CaptureLogger = CaptureLogger {
+        records: std::sync::Mutex::new(Vec::new()),
+    };
+
+    #[test]
+    fn test_add_block_emits_debug_record() {
+        if log::set_logger(&LOGGER).is_ok() {
+            log::set_max_level(log::LevelFilter::Trace);
+        }
+        let mut chain = Blockchain::new();
+        chain.add_block(vec![dummy_tx([1; 32], [2; 32], 1)]);
+        let hash = hex::encode(chain.blocks[1].hash);
+        let records = LOGGER.records.lock().unwrap();
+        assert!(records.iter().any(|(level, message)| {
+            *level == log::Level::Debug && message.contains("блок #1") && message.contains(&hash)
+        }));
+    }
+
+    #[test]
+    fn test_on_block_added_fires_once_per_block() {
+        use std::sync::Mutex;
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let mut chain = Blockchain::new();
+        let sink = Arc::clone(&seen);
+        chain.on_block_added(Box::new(move |block| {
+            sink.lock().unwrap().push(block.index)
+        }));
+
+        chain.add_block(vec![dummy_tx([1; 32], [2; 32], 1)]);
+        chain
+            .add_block_validated(vec![dummy_tx([3; 32], [2; 32], 1)])
+            .unwrap();
+        assert!(
+            chain
+                .try_add_block(vec![dummy_tx([4; 32], [2; 32], 0)])
+                .is_err()
+        );
+        chain.add_block_pow(vec![dummy_tx([5; 32], [2; 32], 1)], 1);
+
+        assert_eq!(*seen.lock().unwrap(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_prune_before_keeps_headers_valid() {
+        let mut chain = chain_with_blocks(1, 5, 1);
+        let hashes: Vec<[u8; 32]> = chain.iter().map(|block| block.hash).collect();
+        chain.prune_before(3);
+        assert_eq!(chain.pruned_before(), 3);
+
+        assert!(chain.blocks[1..3].iter().all(|b| b.transactions.is_empty()));
+        assert_eq!(chain.blocks[3].transactions.len(), 1);
+        assert_eq!(chain.iter().map(|b| b.hash).collect::<Vec<_>>(), hashes);
+        assert!(chain.validate().is_ok());
+
+        let restored = deserialize_blockchain(&serialize_blockchain(&chain).unwrap()).unwrap();
+        assert!(restored.is_valid());
+        assert_eq!(restored.pruned_before(), 3);
+
+        // Заголовки удалённых блоков по-прежнему защищены хешем.
+        chain.blocks[1].merkle_root = [7; 32];
+        assert!(matches!(
+            chain.validate(),
+            Err(ChainError::HashMismatch { index: 1 })
+        ));
+    }
+
+    #[test]
+    fn test_prune_before_keeps_balances_and_nonces() {
+        let mut chain = chain_with_blocks(1, 4, 1);
+        let balances = chain.balances();
+        let replayed = chain.blocks[1].transactions[0].clone();
+        chain.prune_before(3);
+        assert_eq!(chain.balances(), balances);
+        assert_eq!(chain.balances_cached(), &balances);
+        assert_eq!(chain.next_nonce(&[1; 32]), Some(4));
+        assert!(matches!(
+            chain.try_add_block(vec![replayed.clone()]),
+            Err(ChainError::BadNonce { expected: 4, .. })
+        ));
+
+        let restored = deserialize_blockchain(&serialize_blockchain(&chain).unwrap()).unwrap();
+        assert_eq!(restored.pruned_before(), 3);
+        assert_eq!(restored.balances(), balances);
+        assert_eq!(restored.next_nonce(&[1; 32]), Some(4));
+
+        chain.prune_before(u64::MAX);
+        assert_eq!(chain.pruned_before(), 5);
+        assert_eq!(chain.balances(), balances);
+        assert!(chain.validate().is_ok());
+    }
+
+    #[test]
+    fn test_pruned_boundary_does_not_hide_forged_transactions() {
+        let mut chain = chain_with_blocks(1, 3, 1);
+        chain.prune_before(3);
+        let forged = dummy_tx([9; 32], [7; 32], 1_000_000);
+
+        // Транзакции в блоке ниже границы проверяются как обычно.
+        let mut tampered = chain.clone();
+        tampered.blocks[1].transactions = vec![forged.clone()];
+        assert!(matches!(
+            tampered.validate(),
+            Err(ChainError::MerkleRootMismatch { index: 1 })
+        ));
+
+        // Граница из файла не принимается, если ниже неё есть транзакции.
+        let restored = deserialize_blockchain(&serialize_blockchain(&tampered).unwrap()).unwrap();
+        assert_eq!(restored.pruned_before(), 0);
+        assert!(restored.validate().is_err());
+
+        // Граница за концом цепочки или без согласованного снимка сбрасывается.
+        let mut stretched = chain.clone();
+        stretched.pruned_before = u64::MAX;
+        let restored = deserialize_blockchain(&serialize_blockchain(&stretched).unwrap()).unwrap();
+        assert_eq!(restored.pruned_before(), 0);
+        let mut mismatched = chain.clone();
+        mismatched.pruned_state.index = 0;
+        let restored = deserialize_blockchain(&serialize_blockchain(&mismatched).unwrap()).unwrap();
+        assert_eq!(restored.pruned_before(), 0);
+        assert!(restored.validate().is_err());
+    }
+
+    #[test]
+    fn test_try_replace_ignores_candidate_pruning() {
+        let mut chain = chain_with_blocks(1, 2, 1);
+        let honest = chain.clone();
+        let mut candidate = chain_with_blocks(1, 2, 1);
+        candidate.add_block(vec![dummy_tx([1; 32], [2; 32], 1).with_nonce(2)]);
+        candidate.pruned_before = u64::MAX;
+        candidate.blocks[1].transactions.clear();
+        assert!(matches!(
+            chain.try_replace(candidate),
+            Err(ChainError::MerkleRootMismatch { index: 1 })
+        ));
+        assert_eq!(chain, honest);
+        assert!(chain.validate().is_ok());
+        assert_eq!(chain.pruned_before(), 0);
+
+        // Сокращённая цепочка принимает продолжение, совпадающее с ней
+        // до границы, и сохраняет свой снимок.
+        let full = chain_with_blocks(1, 3, 1);
+        let mut pruned = full.clone();
+        pruned.truncate(3);
+        pruned.prune_before(2);
+        let balances = full.balances();
+        assert!(pruned.try_replace(full.clone()).unwrap());
+        assert_eq!(pruned.pruned_before(), 2);
+        assert!(pruned.blocks[1].transactions.is_empty());
+        assert_eq!(pruned.blocks[3], full.blocks[3]);
+        assert_eq!(pruned.balances(), balances);
+        assert!(pruned.validate().is_ok());
+
+        let fork = chain_with_blocks(1, 4, 2);
+        assert!(matches!(
+            pruned.try_replace(fork),
+            Err(ChainError::PrunedHistoryMismatch { index: 1 })
+        ));
+    }
+
+    #[test]
+    fn test_headers_verify_without_bodies() {
+        let mut chain = chain_with_blocks(1, 3, 1);
+        chain.add_block_pow(vec![dummy_tx([2; 32], [3; 32], 1)], 4);
+        let mut headers = chain.headers();
+        assert_eq!(headers.len(), 5);
+        assert_eq!(headers[4], chain.blocks[4].header());
+        assert!(verify_headers(&headers));
+
+        // Заголовки не зависят от тел блоков.
+        chain.prune_before(5);
+        assert_eq!(chain.headers(), headers);
+
+        let proof = Block::new(9, 9, vec![dummy_tx([1; 32], [2; 32], 1)], [0; 32]);
+        assert!(proof.header().verify());
+
+        headers[2].previous_hash = [9; 32];
+        assert!(!verify_headers(&headers));
+        let mut forged = chain.headers();
+        forged[4].difficulty = 30;
+        assert!(!verify_headers(&forged));
+        assert!(!verify_headers(&[]));
+    }
+
+    #[test]
+    fn test_deterministic_genesis_hash_is_fixed() {
+        let chain = Blockchain::new_deterministic();
+        assert_eq!(
+            chain.chain_id(),
+            "62b14867e4e79d50673d2f7474335229f54c478f56d2a910235e1953c6d29206"
+        );
+        assert_eq!(chain, Blockchain::new_deterministic());
+    }
+
+    #[test]
+    fn test_add_blocks_commits_valid_batch() {
+        let source = ChainBuilder::new()
+            .transfer([1; 32], [2; 32], 1)
+            .transfer([1; 32], [2; 32], 2)
+            .transfer([3; 32], [2; 32], 3)
+            .build();
+        let mut chain = Blockchain::new_deterministic();
+        chain.add_blocks(source.blocks[1..].to_vec()).unwrap();
+        assert_eq!(chain, source);
+        assert!(chain.is_valid());
+        let tip = chain.blocks[3].hash;
+        assert_eq!(chain.get_block_by_hash(&tip).unwrap().index, 3);
+    }
+
+    #[test]
+    fn test_add_blocks_is_all_or_nothing() {
+        let source = ChainBuilder::new()
+            .transfer([1; 32], [2; 32], 1)
+            .transfer([1; 32], [2; 32], 2)
+            .transfer([3; 32], [2; 32], 3)
+            .build();
+        let mut chain = Blockchain::new_deterministic();
+        let mut batch = source.blocks[1..].to_vec();
+        batch[1].previous_hash = [9; 32];
+        assert!(matches!(
+            chain.add_blocks(batch),
+            Err(ChainError::BrokenLink { index: 2 })
+        ));
+        assert_eq!(chain.len(), 1);
+        assert!(chain.get_block_by_hash(&source.blocks[1].hash).is_none());
+        assert!(chain.is_valid());
+    }
+
+    #[test]
+    fn test_add_blocks_checks_transactions_against_state() {
+        let source = ChainBuilder::new().transfer([1; 32], [2; 32], 1).build();
+        let previous = source.blocks[1].clone();
+        let mut replay = Block::new(
+            2,
+            previous.timestamp + 1,
+            previous.transactions.clone(),
+            previous.hash,
+        );
+        replay.mine(previous.difficulty as usize).unwrap();
+
+        let mut chain = Blockchain::new_deterministic();
+        assert!(matches!(
+            chain.add_blocks(vec![previous.clone(), replay.clone()]),
+            Err(ChainError::BadNonce {
+                expected: 1,
+                got: 0,
+                ..
+            })
+        ));
+        assert_eq!(chain.len(), 1);
+
+        // Те же правила действуют для тел, прикреплённых к заголовкам.
+        chain.add_blocks(vec![previous]).unwrap();
+        chain.import_headers(vec![replay.header()]).unwrap();
+        assert!(matches!(
+            chain.attach_bodies(vec![replay.transactions]),
+            Err(ChainError::BadNonce { .. })
+        ));
+        assert_eq!(chain.len(), 2);
+    }
+
+    #[test]
+    fn test_headers_first_sync() {
+        let source = ChainBuilder::new()
+            .transfer([1; 32], [2; 32], 1)
+            .transfer([1; 32], [2; 32], 2)
+            .transfer([3; 32], [2; 32], 3)
+            .build();
+        let mut chain = Blockchain::new_deterministic();
+        chain
+            .import_headers(source.headers()[1..].to_vec())
+            .unwrap();
+        assert_eq!(chain.len(), 1);
+        assert_eq!(chain.pending_headers().len(), 3);
+
+        let bodies: Vec<_> = source.blocks[1..]
+            .iter()
+            .map(|block| block.transactions.clone())
+            .collect();
+        chain.attach_bodies(bodies[..2].to_vec()).unwrap();
+        assert_eq!(chain.len(), 3);
+        assert_eq!(chain.pending_headers().len(), 1);
+        chain.attach_bodies(bodies[2..].to_vec()).unwrap();
+        assert!(chain.pending_headers().is_empty());
+        assert_eq!(chain, source);
+        assert!(chain.is_valid());
+    }
+
+    #[test]
+    fn test_import_headers_rejects_broken_headers() {
+        let source = chain_with_blocks(1, 3, 1);
+        let mut chain = Blockchain::new_with_timestamp(1);
+        let headers = source.headers()[1..].to_vec();
+
+        let mut broken = headers.clone();
+        broken[1].previous_hash = [9; 32];
+        assert!(matches!(
+            chain.import_headers(broken),
+            Err(ChainError::BrokenLink { index: 2 })
+        ));
+        let mut tampered = headers.clone();
+        tampered[2].merkle_root = [9; 32];
+        assert!(matches!(
+            chain.import_headers(tampered),
+            Err(ChainError::HashMismatch { index: 3 })
+        ));
+        assert!(matches!(
+            chain.import_headers(headers[1..].to_vec()),
+            Err(ChainError::NonMonotonicIndex { index: 2 })
+        ));
+        assert!(chain.pending_headers().is_empty());
+    }
+
+    #[test]
+    fn test_attach_bodies_checks_merkle_root() {
+        let source = chain_with_blocks(1, 2, 1);
+        let mut chain = Blockchain::new_with_timestamp(1);
+        chain
+            .import_headers(source.headers()[1..].to_vec())
+            .unwrap();
+
+        let wrong = vec![dummy_tx([5; 32], [6; 32], 1)];
+        assert!(matches!(
+            chain.attach_bodies(vec![wrong]),
+            Err(ChainError::MerkleRootMismatch { index: 1 })
+        ));
+        let bodies: Vec<_> = source.blocks[1..]
+            .iter()
+            .map(|block| block.transactions.clone())
+            .collect();
+        let mut extra = bodies.clone();
+        extra.push(Vec::new());
+        assert!(matches!(
+            chain.attach_bodies(extra),
+            Err(ChainError::MissingHeader { index: 3 })
+        ));
+        assert_eq!(chain.len(), 1);
+        chain.attach_bodies(bodies).unwrap();
+        assert_eq!(chain, source);
+    }
+
+    #[test]
+    fn test_ancestry_of_tip_reaches_genesis() {
+        let mut chain = chain_with_blocks(1, 4, 1);
+        let tip = chain.blocks[4].hash;
+        assert!(chain.contains_hash(&tip));
+        assert!(!chain.contains_hash(&[9; 32]));
+
+        let path = chain.ancestry(&tip).unwrap();
+        assert_eq!(path.len(), chain.len());
+        assert_eq!(path[0], tip);
+        assert_eq!(*path.last().unwrap(), chain.genesis_hash());
+        assert_eq!(chain.ancestry(&chain.blocks[2].hash).unwrap().len(), 3);
+        assert_eq!(chain.ancestry(&[9; 32]), None);
+
+        chain.blocks[2].previous_hash = [9; 32];
+        assert_eq!(chain.ancestry(&tip), None);
+    }
+
+    #[test]
+    fn test_multi_round_consensus_waits_for_slow_peers() {
+        let consensus = FixedPeerConsensus::new(vec![
+            Peer::new(1),
+            Peer::approving_from_round(2, 2),
+            Peer::approving_from_round(3, 2),
+        ]);
+        let mut chain = Blockchain::new();
+        let txs = vec![dummy_tx([1; 32], [2; 32], 1)];
+        assert!(!consensus.propose_block(txs.clone(), &mut chain));
+        assert_eq!(
+            consensus.propose_block_rounds(txs.clone(), &mut chain, 1),
+            None
+        );
+        assert_eq!(chain.len(), 1);
+
+        assert_eq!(consensus.propose_block_rounds(txs, &mut chain, 3), Some(2));
+        assert_eq!(chain.len(), 2);
+    }
+
+    #[test]
+    fn test_tally_lists_peers_by_vote() {
+        let consensus = FixedPeerConsensus::new(vec![
+            Peer::new(1),
+            Peer::dishonest(2),
+            Peer::new(3),
+            Peer::dishonest(4),
+            Peer::new(5),
+        ]);
+        let tally = consensus.tally(&[dummy_tx([1; 32], [2; 32], 1)]);
+        assert_eq!(tally.approvals, vec![1, 3, 5]);
+        assert_eq!(tally.rejections, vec![2, 4]);
+        assert_eq!(tally.threshold, 3);
+        assert!(tally.is_approved());
+
+        let empty = FixedPeerConsensus::new(Vec::new()).tally(&[]);
+        assert!(empty.approvals.is_empty() && !empty.is_approved());
+    }
+
+    #[test]
+    fn test_genesis_allocation_funds_addresses() {
+        let alice = [1; 32];
+        let bob = [2; 32];
+        let mut chain = Blockchain::new_with_allocation(vec![(alice, 100), (bob, 5), (alice, 100)])
+            .with_overdraft_protection(true);
+        assert!(chain.is_valid());
+        assert_eq!(chain.balance_of(&alice), 200);
+        assert_eq!(chain.balance_of(&bob), 5);
+        assert_eq!(chain.balance_of(&COINBASE_ADDRESS), 0);
+
+        chain
+            .try_add_block(vec![dummy_tx(alice, bob, 150)])
+            .unwrap();
+        assert_eq!(chain.balance_of(&alice), 50);
+        assert_eq!(chain.balance_of(&bob), 155);
+        assert!(chain.is_valid());
+    }
+
+    #[test]
+    fn test_coinbase_rejected_outside_genesis() {
+        let mut chain = Blockchain::new_with_allocation(vec![([1; 32], 10)]);
+        let err = chain
+            .try_add_block(vec![
+                dummy_tx([1; 32], [2; 32], 1),
+                Transaction::coinbase([3; 32], 50),
+            ])
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ChainError::UnexpectedCoinbase {
+                index: 1,
+                position: 1
+            }
+        ));
+
+        chain.add_block(vec![dummy_tx([1; 32], [2; 32], 1)]);
+        chain.blocks[1].transactions[0] = Transaction::coinbase([3; 32], 50);
+        chain.blocks[1].merkle_root = merkle_root(&chain.blocks[1].transactions);
+        chain.blocks[1].address_filter = address_filter(&chain.blocks[1].transactions);
+        chain.blocks[1].hash = chain.blocks[1].calculate_hash();
+        assert!(matches!(
+            chain.validate(),
+            Err(ChainError::UnexpectedCoinbase {
+                index: 1,
+                position: 0
+            })
+        ));
+
+        let mut genesis = create_genesis_block(0, &Sha256Hasher);
+        genesis.transactions.push(dummy_tx([1; 32], [2; 32], 1));
+        genesis.merkle_root = merkle_root(&genesis.transactions);
+        genesis.hash = genesis.calculate_hash();
+        assert!(matches!(
+            Blockchain::with_genesis(genesis),
+            Err(ChainError::InvalidGenesis)
+        ));
+    }
+
+    #[test]
+    fn test_block_reward_credits_miner() {
+        let miner = [7; 32];
+        let mut chain = Blockchain::new().with_block_reward(miner, 50);
+        for i in 1..=3u8 {
+            chain.add_block(vec![dummy_tx([i; 32], [9; 32], 1)]);
+            assert_eq!(chain.balance_of(&miner), 50 * i as i128);
+            assert_eq!(chain.blocks[i as usize].transactions.len(), 2);
+        }
+        assert!(chain.is_valid());
+
+        chain.blocks[2].transactions[0].amount = 500;
+        chain.blocks[2].merkle_root = merkle_root(&chain.blocks[2].transactions);
+        chain.blocks[2].hash = chain.blocks[2].calculate_hash();
+        assert!(matches!(
+            chain.validate(),
+            Err(ChainError::InvalidReward {
+                index: 2,
+                expected: 50,
+                actual: 500
+            })
+        ));
+    }
+
+    #[test]
+    fn test_second_coinbase_in_block_rejected() {
+        let mut chain = Blockchain::new().with_block_reward([7; 32], 50);
+        let err = chain
+            .try_add_block(vec![Transaction::coinbase([8; 32], 50)])
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ChainError::UnexpectedCoinbase {
+                index: 1,
+                position: 1
+            }
+        ));
+        assert_eq!(chain.len(), 1);
+    }
+
+    #[test]
+    fn test_fees_go_to_miner() {
+        let (alice, bob, miner) = ([1; 32], [2; 32], [7; 32]);
+        let mut chain = Blockchain::new_with_allocation(vec![(alice, 100)])
+            .with_block_reward(miner, 10)
+            .with_overdraft_protection(true);
+        chain
+            .try_add_block(vec![dummy_tx(alice, bob, 30).with_fee(5)])
+            .unwrap();
+        assert_eq!(chain.blocks[1].total_fees(), 5);
+        assert_eq!(chain.blocks[1].transactions[0].amount, 15);
+        assert_eq!(chain.balance_of(&alice), 65);
+        assert_eq!(chain.balance_of(&bob), 30);
+        assert_eq!(chain.balance_of(&miner), 15);
+        assert_eq!(chain.balances().values().sum::<i128>(), 110);
+        assert!(chain.is_valid());
+
+        assert!(matches!(
+            chain.try_add_block(vec![dummy_tx(alice, bob, 60).with_fee(6).with_nonce(1)]),
+            Err(ChainError::InsufficientFunds {
+                needed: 66,
+                available: 65,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_total_fees_overflow() {
+        let txs = vec![
+            dummy_tx([1; 32], [2; 32], 1).with_fee(u64::MAX),
+            dummy_tx([3; 32], [2; 32], 1).with_fee(1),
+        ];
+        let block = Block::new(1, 0, txs.clone(), [0; 32]);
+        assert_eq!(block.total_fees(), u64::MAX);
+        let mut chain = Blockchain::new().with_block_reward([7; 32], 0);
+        assert!(matches!(
+            chain.try_add_block(txs),
+            Err(ChainError::AmountOverflow)
+        ));
+    }
+
+    #[test]
+    fn test_reports_on_empty_chain_do_not_panic() {
+        let mut chain = Blockchain::new();
+        chain.blocks.clear();
+        let chain = deserialize_blockchain(&serialize_blockchain(&chain).unwrap()).unwrap();
+        assert!(chain.is_empty());
+        assert!(!chain.is_valid());
+        assert_eq!(
+            chain.get_chain_info(),
+            "Блоков: 0, Валидно: false, Последний хеш: нет"
+        );
+        let stats = chain.stats();
+        assert_eq!(stats.block_count, 0);
+        assert_eq!(stats.average_block_size_bytes, 0);
+        assert_eq!(stats.last_hash, None);
+        assert!(!stats.is_valid);
+    }
+
+    #[test]
+    fn test_checked_deserialization_rejects_tampered_chain() {
+        let mut chain = chain_with_blocks(1, 3, 1);
+        let restored =
+            deserialize_blockchain_checked(&serialize_blockchain(&chain).unwrap()).unwrap();
+        assert_eq!(restored, chain);
+
+        chain.blocks[2].transactions[0].amount += 1;
+        let bytes = serialize_blockchain(&chain).unwrap();
+        assert!(deserialize_blockchain(&bytes).is_ok());
+        assert!(matches!(
+            deserialize_blockchain_checked(&bytes),
+            Err(ChainError::MerkleRootMismatch { index: 2 })
+        ));
+
+        chain.blocks.clear();
+        assert!(matches!(
+            deserialize_blockchain_checked(&serialize_blockchain(&chain).unwrap()),
+            Err(ChainError::EmptyChain)
+        ));
+    }
+
+    #[test]
+    fn test_tip_of_empty_chain_is_none() {
+        let mut chain = Blockchain::new();
+        assert_eq!(chain.tip(), chain.blocks.first());
+        assert_eq!(chain.height(), Some(0));
+        chain.add_block(vec![dummy_tx([1; 32], [2; 32], 1)]);
+        assert_eq!(
+            chain.tip().map(|block| block.hash),
+            Some(chain.blocks[1].hash)
+        );
+        assert_eq!(chain.height(), Some(1));
+
+        chain.blocks.clear();
+        assert_eq!(chain.tip(), None);
+        assert_eq!(chain.height(), None);
+        assert!(matches!(
+            chain.try_add_block(vec![dummy_tx([1; 32], [2; 32], 2)]),
+            Err(ChainError::EmptyChain)
+        ));
+    }
+
+    #[test]
+    fn test_address_hex_roundtrip() {
+        let addr: [u8; 32] = std::array::from_fn(|i| i as u8 * 7);
+        let hex = address_to_hex(&addr);
+        assert_eq!(hex.len(), 64);
+        assert_eq!(parse_address(&hex).unwrap(), addr);
+        assert_eq!(parse_address(&hex.to_uppercase()).unwrap(), addr);
+
+        for bad in [
+            "",
+            "ab",
+            &hex[..62],
+            &format!("{hex}00"),
+            &hex.replace('0', "g"),
+        ] {
+            assert!(
+                matches!(parse_address(bad), Err(ChainError::InvalidAddress { ref input }) if input == bad),
+                "{bad:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_vanity_address_has_requested_prefix() {
+        let (secret, addr) = generate_vanity_address("a", 1_000).unwrap();
+        assert!(address_to_hex(&addr).starts_with('a'));
+        assert_eq!(
+            SigningKey::from_bytes(&secret).verifying_key().to_bytes(),
+            addr
+        );
+        assert_eq!(generate_vanity_address("A", 1_000), Some((secret, addr)));
+
+        let (_, addr) = generate_vanity_address("", 1).unwrap();
+        assert!(!address_to_hex(&addr).is_empty());
+        assert_eq!(generate_vanity_address("a", 0), None);
+        assert_eq!(generate_vanity_address("xy", 1_000), None);
+        assert_eq!(generate_vanity_address(&"0".repeat(65), 1_000), None);
+    }
+
+    #[test]
+    fn test_txid_identifies_transaction() {
+        let tx = dummy_tx([1; 32], [2; 32], 10);
+        assert_eq!(tx.txid(), tx.clone().txid());
+        assert_ne!(tx.txid(), tx.clone().with_nonce(1).txid());
+        assert_ne!(tx.txid(), dummy_tx([1; 32], [2; 32], 11).txid());
+        assert_ne!(tx.txid(), tx.clone().with_fee(1).txid());
+
+        let mut chain = Blockchain::new();
+        chain.add_block(vec![dummy_tx([3; 32], [4; 32], 1), tx.clone()]);
+        assert_eq!(chain.get_transaction_by_id(&tx.txid()), Some(&tx));
+        assert_eq!(chain.find_transaction(&tx), Some((1, 1)));
+        assert_eq!(chain.get_transaction_by_id(&[0xab; 32]), None);
+
+        let fresh = dummy_tx([5; 32], [6; 32], 1);
+        let mut resigned = fresh.clone();
+        resigned.signature = [1; 64];
+        assert!(matches!(
+            chain.try_add_block(vec![dummy_tx([7; 32], [8; 32], 1), fresh, resigned]),
+            Err(ChainError::DuplicateTransaction { position: 2 })
+        ));
+    }
+
+    #[test]
+    fn test_snapshot_applies_blocks_incrementally() {
+        let (alice, bob, carol) = ([1; 32], [2; 32], [3; 32]);
+        let mut chain = Blockchain::new_with_allocation(vec![(alice, 100)]);
+        chain.add_block(vec![dummy_tx(alice, bob, 40).with_fee(2)]);
+        let mut snapshot = chain.snapshot_at(1);
+        assert_eq!(snapshot.index, 1);
+        assert_eq!(snapshot, chain.snapshot_at(u64::MAX));
+
+        chain.add_block(vec![dummy_tx(bob, carol, 15).with_nonce(0)]);
+        chain.add_block(vec![dummy_tx(alice, carol, 5).with_nonce(1)]);
+        for block in &chain.blocks[2..] {
+            snapshot.apply_block(block).unwrap();
+        }
+        assert_eq!(snapshot.index, 3);
+        assert_eq!(snapshot.balances, chain.balances());
+        assert_eq!(snapshot.balances[&alice], 53);
+        assert_eq!(chain.snapshot_at(1).balances[&bob], 40);
+    }
+
+    #[test]
+    fn test_balances_do_not_overflow_on_large_amounts() {
+        let (alice, bob) = ([1; 32], [2; 32]);
+        let mut chain = Blockchain::new();
+        chain.add_block(vec![dummy_tx(alice, bob, u64::MAX).with_fee(u64::MAX)]);
+        chain.add_block(vec![dummy_tx(alice, bob, u64::MAX).with_nonce(1)]);
+        assert_eq!(chain.balance_of(&bob), 2 * u64::MAX as i128);
+        assert_eq!(chain.balance_of(&alice), -3 * u64::MAX as i128);
+
+        let mut snapshot = StateSnapshot::default();
+        snapshot.balances.insert(bob, i128::MAX);
+        let before = snapshot.clone();
+        assert!(matches!(
+            snapshot.apply_block(&chain.blocks[1]),
+            Err(ChainError::BalanceOverflow { index: 1, addr }) if addr == bob
+        ));
+        assert_eq!(snapshot, before);
+    }
+
+    #[test]
+    fn test_migrate_reads_v1_format() {
+        let chain = chain_with_blocks(1, 3, 1);
+        let legacy_blocks = || -> Vec<BlockV4> {
+            chain
+                .blocks
+                .iter()
+                .map(|block| BlockV4 {
+                    index: block.index,
+                    timestamp: block.timestamp,
+                    transactions: block
+                        .transactions
+                        .iter()
+                        .map(|tx| TransactionV4 {
+                            from: tx.from,
+                            to: tx.to,
+                            amount: tx.amount,
+                            nonce: tx.nonce,
+                            fee: tx.fee,
+                            signature: tx.signature,
+                        })
+                        .collect(),
+                    merkle_root: block.merkle_root,
+                    previous_hash: block.previous_hash,
+                    nonce: block.nonce,
+                    difficulty: block.difficulty,
+                    hash: block.hash,
+                })
+                .collect()
+        };
+        let legacy_config = || ChainConfigV2 {
+            max_transactions_per_block: chain.config.max_transactions_per_block,
+            mining: None,
+            overdraft_protection: false,
+            verify_signatures: false,
+            min_block_interval: None,
+            max_block_interval: None,
+            allow_equal_timestamps: false,
+            block_reward: 0,
+            miner: None,
+        };
+        let legacy_config_v7 = || ChainConfigV7 {
+            max_transactions_per_block: chain.config.max_transactions_per_block,
+            mining: None,
+            overdraft_protection: false,
+            verify_signatures: false,
+            min_block_interval: None,
+            max_block_interval: None,
+            allow_equal_timestamps: false,
+            block_reward: 0,
+            miner: None,
+            genesis_difficulty: 0,
+            max_block_bytes: None,
+        };
+        let v1 = bincode::serialize(&BlockchainV1 {
+            blocks: legacy_blocks(),
+            config: legacy_config(),
+            pruned_before: 0,
+        })
+        .unwrap();
+        let v2 = bincode::serialize(&VersionedChain {
+            version: 2,
+            blocks: legacy_blocks(),
+            config: legacy_config(),
+            pruned_before: 0,
+        })
+        .unwrap();
+        assert_eq!(Blockchain::migrate(&v2).unwrap(), chain);
+        let v3 = bincode::serialize(&VersionedChain {
+            version: 3,
+            blocks: legacy_blocks(),
+            config: ChainConfigV3 {
+                max_transactions_per_block: chain.config.max_transactions_per_block,
+                mining: None,
+                overdraft_protection: false,
+                verify_signatures: false,
+                min_block_interval: None,
+                max_block_interval: None,
+                allow_equal_timestamps: false,
+                block_reward: 0,
+                miner: None,
+                genesis_difficulty: 0,
+            },
+            pruned_before: 0,
+        })
+        .unwrap();
+        assert_eq!(Blockchain::migrate(&v3).unwrap(), chain);
+        let v4 = bincode::serialize(&VersionedChain {
+            version: 4,
+            blocks: legacy_blocks(),
+            config: legacy_config_v7(),
+            pruned_before: 0,
+        })
+        .unwrap();
+        assert_eq!(Blockchain::migrate(&v4).unwrap(), chain);
+        let v5 = bincode::serialize(&VersionedChain {
+            version: 5,
+            blocks: chain
+                .blocks
+                .iter()
+                .map(|block| BlockV5 {
+                    index: block.index,
+                    timestamp: block.timestamp,
+                    transactions: block.transactions.clone(),
+                    merkle_root: block.merkle_root,
+                    previous_hash: block.previous_hash,
+                    nonce: block.nonce,
+                    difficulty: block.difficulty,
+                    hash: block.hash,
+                })
+                .collect(),
+            config: legacy_config_v7(),
+            pruned_before: 0,
+        })
+        .unwrap();
+        assert_eq!(Blockchain::migrate(&v5).unwrap(), chain);
+        let v6 = bincode::serialize(&VersionedChain {
+            version: 6,
+            blocks: chain
+                .blocks
+                .iter()
+                .map(|block| BlockV6 {
+                    index: block.index,
+                    timestamp: block.timestamp,
+                    transactions: block.transactions.clone(),
+                    merkle_root: block.merkle_root,
+                    previous_hash: block.previous_hash,
+                    nonce: block.nonce,
+                    difficulty: block.difficulty,
+                    hash: block.hash,
+                    metadata: block.metadata.clone(),
+                })
+                .collect(),
+            config: legacy_config_v7(),
+            pruned_before: 0,
+        })
+        .unwrap();
+        assert_eq!(Blockchain::migrate(&v6).unwrap(), chain);
+        let v7 = bincode::serialize(&VersionedChain {
+            version: 7,
+            blocks: chain.blocks.clone(),
+            config: legacy_config_v7(),
+            pruned_before: 0,
+        })
+        .unwrap();
+        assert_eq!(Blockchain::migrate(&v7).unwrap(), chain);
+        let v8 = bincode::serialize(&VersionedChain {
+            version: 8,
+            blocks: chain.blocks.clone(),
+            config: chain.config.clone(),
+            pruned_before: 0,
+        })
+        .unwrap();
+        assert_eq!(Blockchain::migrate(&v8).unwrap(), chain);
+        let mut v9 = serialize_blockchain(&chain).unwrap();
+        v9[..2].copy_from_slice(&9u16.to_le_bytes());
+        assert_eq!(Blockchain::migrate(&v9).unwrap(), chain);
+        let migrated = Blockchain::migrate(&v1).unwrap();
+        assert_eq!(migrated, chain);
+        assert_eq!(migrated.version(), CHAIN_FORMAT_VERSION);
+        assert_eq!(
+            migrated.get_block_by_hash(&chain.blocks[2].hash),
+            Some(&chain.blocks[2])
+        );
+
+        let current = serialize_blockchain(&chain).unwrap();
+        assert_eq!(&current[..2], &CHAIN_FORMAT_VERSION.to_le_bytes());
+        assert_eq!(Blockchain::migrate(&current).unwrap(), chain);
+
+        let mut future = current;
+        future[..2].copy_from_slice(&(CHAIN_FORMAT_VERSION + 1).to_le_bytes());
+        assert!(matches!(
+            Blockchain::migrate(&future),
+            Err(ChainError::UnsupportedVersion { version }) if version == CHAIN_FORMAT_VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn test_migrate_rehashes_little_endian_fixture() {
+        // Цепочка версии 6, записанная до перехода хеширования на big-endian:
+        // генезис с метаданными, мультиподпись и блок со сложностью 8.
+        let bytes: &[u8] = include_bytes!("../tests/fixtures/chain_v6.bin");
+        let old_tip =
+            hex::decode("b95dcd621db79b2c5e92076fcc3d056b42aafa6118f8a60ee3ae8bdb44b24033")
+                .unwrap();
+
+        let chain = Blockchain::migrate(bytes).unwrap();
+        assert_eq!(chain.version(), CHAIN_FORMAT_VERSION);
+        assert_eq!(chain.len(), 4);
+        assert_ne!(chain.tip().unwrap().hash.as_slice(), old_tip.as_slice());
+        assert_eq!(chain.blocks[0].metadata_str(), Some("fixture"));
+        assert!(chain.blocks[1].transactions[1].multisig.is_some());
+        assert_eq!(chain.blocks[2].difficulty, 8);
+        assert!(chain.blocks[2].meets_difficulty());
+        assert_eq!(chain.balance_of(&[3; 32]), 2);
+        assert!(chain.is_valid());
+        let current = serialize_blockchain(&chain).unwrap();
+        assert_eq!(Blockchain::migrate(&current).unwrap(), chain);
+
+        // Повреждённая цепочка не становится корректной после пересчёта хешей.
+        let mut legacy: VersionedChain<ChainConfigV7, BlockV6> =
+            bincode::deserialize(bytes).unwrap();
+        legacy.blocks[1].transactions[0].amount = 500;
+        let tampered = bincode::serialize(&legacy).unwrap();
+        assert!(Blockchain::migrate(&tampered).is_err());
+    }
+
+    #[test]
+    fn test_validate_enforces_genesis_difficulty() {
+        let config = ChainConfig {
+            genesis_difficulty: 6,
+            ..ChainConfig::default()
+        };
+        let mut chain = Blockchain::with_config(config);
+        assert_eq!(chain.blocks[0].difficulty, 6);
+        assert!(chain.blocks[0].meets_difficulty());
+        assert!(chain.is_valid());
+
+        chain.config.genesis_difficulty = 7;
+        assert!(matches!(
+            chain.validate(),
+            Err(ChainError::DifficultyMismatch {
+                index: 0,
+                expected: 7,
+                actual: 6
+            })
+        ));
+
+        chain.config.genesis_difficulty = 6;
+        while chain.blocks[0].meets_difficulty() {
+            chain.blocks[0].nonce += 1;
+            chain.blocks[0].hash = chain.blocks[0].calculate_hash();
+        }
+        assert!(matches!(
+            chain.validate(),
+            Err(ChainError::InsufficientWork { index: 0 })
+        ));
+    }
+
+    #[test]
+    fn test_clone_is_independent_of_original() {
+        let original = chain_with_blocks(1, 3, 1);
+        let mut copy = original.clone();
+        assert_eq!(copy, original);
+
+        copy.blocks[2].transactions[0].amount += 1;
+        copy.add_block(vec![dummy_tx([5; 32], [6; 32], 1)]);
+        assert!(!copy.is_valid());
+        assert_eq!(original.len(), 4);
+        assert_eq!(original.blocks[2].transactions[0].amount, 1);
+        assert!(original.is_valid());
+    }
+
+    #[test]
+    fn test_block_interval_and_throughput() {
+        let chain = ChainBuilder::new()
+            .transfer([1; 32], [2; 32], 1)
+            .block(vec![
+                dummy_tx([3; 32], [4; 32], 1),
+                dummy_tx([5; 32], [4; 32], 1),
+            ])
+            .transfer([1; 32], [2; 32], 2)
+            .build();
+        assert_eq!(chain.average_block_interval(), Some(NANOS_PER_SEC));
+        assert_eq!(chain.transactions_per_second(), 4.0 / 3.0);
+
+        let single = ChainBuilder::new().build();
+        assert_eq!(single.average_block_interval(), None);
+        assert_eq!(single.transactions_per_second(), 0.0);
+    }
+
+    #[test]
+    fn test_block_over_byte_limit_rejected() {
+        let mut chain = Blockchain::with_config(ChainConfig {
+            max_block_bytes: Some(400),
+            ..ChainConfig::default()
+        });
+        chain
+            .try_add_block(vec![dummy_tx([1; 32], [2; 32], 1)])
+            .unwrap();
+        let txs: Vec<_> = (3..=7).map(|i| dummy_tx([i; 32], [9; 32], 1)).collect();
+        assert!(matches!(
+            chain.try_add_block(txs),
+            Err(ChainError::BlockTooLarge { index: 2, size, max: 400 }) if size > 400
+        ));
+        assert_eq!(chain.len(), 2);
+    }
+
+    #[test]
+    fn test_self_transfer_rejected() {
+        let mut chain = Blockchain::new();
+        assert!(matches!(
+            chain.try_add_block(vec![
+                dummy_tx([1; 32], [2; 32], 1),
+                dummy_tx([3; 32], [3; 32], 1)
+            ]),
+            Err(ChainError::SelfTransfer { position: 1 })
+        ));
+        assert_eq!(chain.len(), 1);
+    }
+
+    #[test]
+    fn test_coinbase_is_not_a_self_transfer() {
+        // Награда, «сжигаемая» на адрес coinbase, формально переводит монеты
+        // с адреса на него же
+        let mut chain = Blockchain::new().with_block_reward(COINBASE_ADDRESS, 10);
+        chain
+            .try_add_block(vec![dummy_tx([1; 32], [2; 32], 1)])
+            .unwrap();
+        let coinbase = &chain.blocks[1].transactions[0];
+        assert!(coinbase.is_coinbase() && coinbase.to == coinbase.from);
+        assert!(chain.is_valid());
+    }
+
+    #[test]
+    fn test_deserialize_rejects_huge_transaction_count() {
+        let chain = ChainBuilder::new().transfer([1; 32], [2; 32], 10).build();
+        let mut bytes = serialize_block(&chain.blocks[1]).unwrap();
+        // index и timestamp, затем длина списка транзакций.
+        let len_offset = 8 + 8;
+        assert_eq!(bytes[len_offset..len_offset + 8], 1u64.to_le_bytes());
+        bytes[len_offset..len_offset + 8].copy_from_slice(&(u64::MAX / 2).to_le_bytes());
+        assert!(deserialize_block(&bytes).is_err());
+
+        let mut bytes = serialize_blockchain(&chain).unwrap();
+        // version, затем длина списка блоков.
+        bytes[2..10].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(deserialize_blockchain(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_deserialize_respects_size_limit() {
+        let chain = chain_with_blocks(1_000, 5, 1);
+        let bytes = serialize_blockchain(&chain).unwrap();
+        let limit = bytes.len() as u64 / 2;
+        assert!(matches!(
+            deserialize_blockchain_with_limit(&bytes, limit),
+            Err(ChainError::SizeLimitExceeded { limit: l }) if l == limit
+        ));
+        assert_eq!(
+            deserialize_blockchain_with_limit(&bytes, bytes.len() as u64).unwrap(),
+            chain
+        );
+    }
+
+    #[test]
+    fn test_named_chains_have_different_genesis_hashes() {
+        let chain = Blockchain::new_named("testnet");
+        assert_eq!(chain.name(), Some("testnet"));
+        assert!(chain.is_valid());
+        assert_eq!(Blockchain::new_deterministic().name(), None);
+
+        let main = Blockchain::with_genesis(create_genesis_block_with_metadata(
+            1,
+            b"mainnet",
+            &Sha256Hasher,
+        ))
+        .unwrap();
+        let test = Blockchain::with_genesis(create_genesis_block_with_metadata(
+            1,
+            b"testnet",
+            &Sha256Hasher,
+        ))
+        .unwrap();
+        assert_ne!(main.genesis_hash(), test.genesis_hash());
+        assert_ne!(
+            main.genesis_hash(),
+            Blockchain::new_with_timestamp(1).genesis_hash()
+        );
+        assert_eq!(main.name(), Some("mainnet"));
+        assert!(verify_headers(&main.headers()));
+
+        let restored = deserialize_blockchain(&serialize_blockchain(&main).unwrap()).unwrap();
+        assert_eq!(restored.name(), Some("mainnet"));
+        assert_eq!(restored.genesis_hash(), main.genesis_hash());
+
+        let mut tampered = main.clone();
+        tampered.blocks[0].metadata = b"testnet".to_vec();
+        assert!(matches!(
+            tampered.validate(),
+            Err(ChainError::MerkleRootMismatch { index: 0 })
+        ));
+    }
+
+    #[test]
+    fn test_metadata_is_rejected_after_genesis() {
+        let mut chain = chain_with_blocks(1, 2, 1);
+        chain.blocks[2].metadata = b"name".to_vec();
+        chain.blocks[2].merkle_root =
+            crate::core::body_root(&chain.blocks[2].transactions, &chain.blocks[2].metadata);
+        chain.blocks[2].hash = chain.blocks[2].calculate_hash();
+        assert!(matches!(
+            chain.validate(),
+            Err(ChainError::UnexpectedMetadata { index: 2 })
+        ));
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        let hash = Blockchain::new_deterministic().genesis_hash();
+        assert!(crate::constant_time_eq(&hash, &hash));
+        assert!(crate::constant_time_eq(&[0; 32], &[0; 32]));
+        let mut other = hash;
+        other[31] ^= 1;
+        assert!(!crate::constant_time_eq(&hash, &other));
+        assert!(!crate::constant_time_eq(&[0; 32], &[0xff; 32]));
+    }
+
+    #[test]
+    fn test_blockchain_into_iterator() {
+        let chain = chain_with_blocks(1, 3, 1);
+        let indices: Vec<u64> = (&chain).into_iter().map(|block| block.index).collect();
+        assert_eq!(indices, vec![0, 1, 2, 3]);
+        let mut with_transactions = 0;
+        for block in &chain {
+            if !block.transactions.is_empty() {
+                with_transactions += 1;
+            }
+        }
+        assert_eq!(with_transactions, 3);
+    }
+
+    #[test]
+    fn test_blockchain_from_iterator() {
+        let batches = vec![
+            vec![dummy_tx([1; 32], [2; 32], 10)],
+            Vec::new(),
+            vec![
+                dummy_tx([1; 32], [3; 32], 5).with_nonce(1),
+                dummy_tx([2; 32], [3; 32], 5),
+            ],
+        ];
+        let chain: Blockchain = batches.clone().into_iter().collect();
+        assert_eq!(chain.len(), 4);
+        assert!(chain.is_valid());
+        for (block, batch) in chain.iter().skip(1).zip(&batches) {
+            assert_eq!(&block.transactions, batch);
+        }
+        assert_eq!(chain.balance_of(&[3; 32]), 10);
+    }
+
+    #[test]
+    fn test_range_returns_clamped_slice() {
+        let chain = chain_with_blocks(1, 5, 1);
+        let indices = |blocks: &[Block]| blocks.iter().map(|b| b.index).collect::<Vec<_>>();
+        assert_eq!(indices(chain.range(1, 4)), vec![1, 2, 3]);
+        assert_eq!(indices(chain.range(4, 100)), vec![4, 5]);
+        assert_eq!(indices(chain.range(0, u64::MAX)).len(), 6);
+        assert!(chain.range(10, 20).is_empty());
+        assert!(chain.range(3, 3).is_empty());
+        assert!(chain.range(4, 2).is_empty());
+    }
+
+    #[test]
+    fn test_page_past_end_is_empty() {
+        let chain = chain_with_blocks(1, 5, 1);
+        assert_eq!(chain.page(0, 4).len(), 4);
+        assert_eq!(chain.page(1, 4)[0].index, 4);
+        assert_eq!(chain.page(1, 4).len(), 2);
+        assert!(chain.page(2, 4).is_empty());
+        assert!(chain.page(0, 0).is_empty());
+        assert!(chain.page(usize::MAX, 2).is_empty());
+    }
+
+    #[test]
+    fn test_all_transactions_skips_genesis() {
+        let mut chain = Blockchain::new_with_allocation(vec![([1; 32], 100), ([2; 32], 50)]);
+        chain.add_block(vec![dummy_tx([1; 32], [3; 32], 10)]);
+        chain.add_block(Vec::new());
+        chain.add_block(vec![
+            dummy_tx([1; 32], [3; 32], 5).with_nonce(1),
+            dummy_tx([2; 32], [3; 32], 5),
+        ]);
+        let all: Vec<_> = chain.all_transactions().collect();
+        assert_eq!(all.len(), 3);
+        assert_eq!(
+            all.iter().map(|(index, _)| *index).collect::<Vec<_>>(),
+            vec![1, 3, 3]
+        );
+        assert!(all.iter().all(|(_, tx)| !tx.is_coinbase()));
+        assert_eq!(chain.stats().transaction_count, 3);
+        assert_eq!(chain.stats().total_amount, 20);
+    }
+
+    #[test]
+    fn test_mock_clock_controls_timestamps() {
+        let clock = Arc::new(MockClock::new(1_000 * NANOS_PER_SEC));
+        let mut chain = Blockchain::with_config(ChainConfig {
+            clock: clock.clone(),
+            ..ChainConfig::default()
+        });
+        chain.add_block(vec![dummy_tx([1; 32], [2; 32], 1)]);
+        clock.advance(5 * NANOS_PER_SEC);
+        chain.add_block(Vec::new());
+        clock.set(2_000 * NANOS_PER_SEC);
+        chain
+            .add_block_validated(vec![dummy_tx([1; 32], [2; 32], 1).with_nonce(1)])
+            .unwrap();
+
+        let timestamps: Vec<u64> = chain.iter().map(|block| block.timestamp).collect();
+        assert_eq!(
+            timestamps,
+            vec![
+                1_000 * NANOS_PER_SEC,
+                1_000 * NANOS_PER_SEC + 1,
+                1_005 * NANOS_PER_SEC,
+                2_000 * NANOS_PER_SEC,
+            ]
+        );
+        assert!(chain.is_valid());
+
+        let mut other = Blockchain::new_with_timestamp(0).with_clock(Arc::new(MockClock::new(7)));
+        other.add_block(Vec::new());
+        assert_eq!(other.blocks[1].timestamp, 7);
+
+        clock.advance(u64::MAX);
+        assert_eq!(clock.now_nanos(), u64::MAX);
+    }
+
+    #[test]
+    fn test_estimated_hashrate() {
+        let mut chain = Blockchain::new_with_timestamp(0);
+        assert_eq!(chain.estimated_hashrate(), None);
+        push_block_at(&mut chain, 0, 0);
+        assert_eq!(chain.estimated_hashrate(), None);
+
+        // 2 блока сложности 4 и 2 блока сложности 6 за 8 секунд:
+        // (2 * 16 + 2 * 64) / 8 = 20 хешей в секунду.
+        let mut chain = Blockchain::new_with_timestamp(0);
+        for (i, difficulty) in [4, 4, 6, 6].into_iter().enumerate() {
+            push_block_at(&mut chain, (i as u64 + 1) * 2 * NANOS_PER_SEC, difficulty);
+        }
+        let hashrate = chain.estimated_hashrate().unwrap();
+        assert!((hashrate - 20.0).abs() < 1e-9, "{}", hashrate);
+
+        // Учитываются только последние HASHRATE_WINDOW интервалов.
+        for i in 0..HASHRATE_WINDOW as u64 {
+            push_block_at(&mut chain, 100 * NANOS_PER_SEC + i * NANOS_PER_SEC, 1);
+        }
+        let hashrate = chain.estimated_hashrate().unwrap();
+        let expected = HASHRATE_WINDOW as f64 * 2.0 / (100.0 - 8.0 + 9.0);
+        assert!((hashrate - expected).abs() < 1e-9, "{}", hashrate);
+    }
+
+    #[test]
+    fn test_verify_serialized_reports_stats_or_error() {
+        let chain = chain_with_blocks(1, 3, 1);
+        let bytes = serialize_blockchain(&chain).unwrap();
+        let stats = verify_serialized(&bytes).unwrap();
+        assert_eq!(stats, chain.stats());
+        assert_eq!(stats.block_count, 4);
+        assert!(stats.is_valid);
+
+        let mut tampered = chain.clone();
+        tampered.blocks[2].transactions[0].amount = 50;
+        let bytes = serialize_blockchain(&tampered).unwrap();
+        assert!(matches!(
+            verify_serialized(&bytes),
+            Err(ChainError::MerkleRootMismatch { index: 2 })
+        ));
+        assert!(matches!(
+            verify_serialized(&bytes[..bytes.len() / 2]),
+            Err(ChainError::Serialization(_))
+        ));
+    }
+
+    #[derive(Debug)]
+    struct MaxAmount(u64);
+
+    impl TxValidator for MaxAmount {
+        fn validate(&self, tx: &Transaction, _chain: &Blockchain) -> Result<(), ChainError> {
+            if tx.amount > self.0 {
+                return Err(ChainError::TransactionRejected {
+                    reason: format!("сумма {} больше {}", tx.amount, self.0),
+                });
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_custom_tx_validator_rejects_block() {
+        let mut chain = Blockchain::with_config(ChainConfig {
+            tx_validator: Arc::new(MaxAmount(100)),
+            ..ChainConfig::default()
+        });
+        chain
+            .try_add_block(vec![dummy_tx([1; 32], [2; 32], 100)])
+            .unwrap();
+        let result = chain.try_add_block(vec![
+            dummy_tx([1; 32], [2; 32], 5).with_nonce(1),
+            dummy_tx([3; 32], [2; 32], 101),
+        ]);
+        assert!(matches!(
+            result,
+            Err(ChainError::TransactionRejected { ref reason }) if reason.contains("101")
+        ));
+        assert_eq!(chain.len(), 2);
+
+        let mut unrestricted = Blockchain::new();
+        unrestricted
+            .try_add_block(vec![dummy_tx([3; 32], [2; 32], 101)])
+            .unwrap();
+    }
+
+    #[test]
+    fn test_rebuild_hashes_makes_crafted_chain_valid() {
+        let source = chain_with_blocks(1, 3, 1);
+        let mut blocks = source.blocks.clone();
+        blocks[1].transactions[0].amount = 500;
+        let extra = Block::new(9, blocks[2].timestamp + 1, vec![], [7; 32]);
+        blocks.insert(3, extra);
+
+        let mut chain = Blockchain::from_blocks_unchecked(blocks);
+        assert_eq!(chain.len(), 5);
+        assert!(matches!(
+            chain.validate(),
+            Err(ChainError::MerkleRootMismatch { index: 1 })
+        ));
+
+        chain.rebuild_hashes();
+        assert!(chain.is_valid());
+        assert_eq!(chain.blocks[3].index, 3);
+        assert_eq!(chain.blocks[0], source.blocks[0]);
+        assert_ne!(chain.blocks[1].hash, source.blocks[1].hash);
+        let tip = chain.blocks[4].hash;
+        assert_eq!(chain.get_block_by_hash(&tip).unwrap().index, 4);
+    }
+
+    #[test]
+    fn test_rebuild_hashes_remines_blocks() {
+        let mut chain = Blockchain::new_with_timestamp(1);
+        chain.add_block_pow(vec![dummy_tx([1; 32], [2; 32], 1)], 6);
+        chain.add_block_pow(vec![dummy_tx([1; 32], [2; 32], 1).with_nonce(1)], 6);
+        chain.blocks[1].transactions[0].amount = 2;
+        assert!(!chain.is_valid());
+
+        chain.rebuild_hashes();
+        assert!(chain.is_valid());
+        assert!(
+            chain
+                .blocks
+                .iter()
+                .skip(1)
+                .all(|block| block.difficulty == 6)
+        );
+        assert!(chain.blocks.iter().all(Block::meets_difficulty));
+    }
+
+    #[test]
+    fn test_address_filter_has_no_false_negatives() {
+        let chain = chain_with_blocks(1, 20, 1);
+        for block in &chain.blocks {
+            for tx in &block.transactions {
+                assert!(block.may_contain_address(&tx.from));
+                assert!(block.may_contain_address(&tx.to));
+            }
+        }
+
+        let absent: Vec<[u8; 32]> = (200..=255u8).map(|i| [i; 32]).collect();
+        let hits = chain
+            .blocks
+            .iter()
+            .flat_map(|block| absent.iter().filter(|addr| block.may_contain_address(addr)))
+            .count();
+        let checks = absent.len() * chain.len();
+        assert!(
+            hits * 100 < checks,
+            "ложных срабатываний: {hits} из {checks}"
+        );
+        assert!(!chain.blocks[0].may_contain_address(&[1; 32]));
+        assert_eq!(chain.transactions_for_address(&[105; 32]).len(), 1);
+    }
+
+    #[test]
+    fn test_tampered_address_filter_is_rejected() {
+        let mut chain = chain_with_blocks(1, 3, 1);
+        chain.blocks[2].address_filter = [0; crate::ADDRESS_FILTER_BYTES];
+        assert!(matches!(
+            chain.validate(),
+            Err(ChainError::AddressFilterMismatch { index: 2 })
+        ));
+        assert_eq!(chain.transactions_for_address(&[1; 32]).len(), 2);
+
+        chain.rebuild_hashes();
+        assert!(chain.is_valid());
+        assert_eq!(chain.transactions_for_address(&[1; 32]).len(), 3);
+    }
+
+    #[test]
+    fn test_block_builder_root_matches_batch_merkle_root() {
+        let genesis = create_genesis_block(1, &Sha256Hasher);
+        let txs: Vec<_> = (0..40u64)
+            .map(|i| dummy_tx([1; 32], [2; 32], i + 1).with_nonce(i))
+            .collect();
+        let mut builder = BlockBuilder::new(&genesis);
+        assert_eq!(builder.merkle_root(), merkle_root(&[]));
+        for (count, tx) in txs.iter().enumerate() {
+            builder.add_transaction(tx.clone());
+            assert_eq!(
+                builder.merkle_root(),
+                merkle_root(&txs[..=count]),
+                "{} транзакций",
+                count + 1
+            );
+        }
+
+        let block = builder.build(2);
+        assert_eq!(block, Block::new(1, 2, txs, genesis.hash));
+    }
+
+    #[test]
+    fn test_common_ancestor_and_diff_of_forks() {
+        let base = chain_with_blocks(1, 2, 1);
+        let mut left = base.clone();
+        left.add_block(vec![dummy_tx([2; 32], [3; 32], 1)]);
+        left.add_block(vec![dummy_tx([2; 32], [3; 32], 2).with_nonce(1)]);
+        let mut right = base.clone();
+        right.add_block(vec![dummy_tx([4; 32], [5; 32], 1)]);
+
+        assert_eq!(left.common_ancestor(&right), Some(2));
+        assert_eq!(right.common_ancestor(&left), Some(2));
+        let (ours, theirs) = left.diff(&right);
+        assert_eq!(ours, left.blocks[3..]);
+        assert_eq!(theirs, right.blocks[3..]);
+        assert_eq!(
+            ours.iter().map(|block| block.index).collect::<Vec<_>>(),
+            vec![3, 4]
+        );
+
+        assert_eq!(left.common_ancestor(&base), Some(2));
+        assert_eq!(left.diff(&base), (left.blocks[3..].to_vec(), Vec::new()));
+        assert_eq!(left.common_ancestor(&left), Some(4));
+        assert!(left.diff(&left).0.is_empty());
+
+        let unrelated = chain_with_blocks(2, 1, 1);
+        assert_eq!(left.common_ancestor(&unrelated), None);
+        let (ours, theirs) = left.diff(&unrelated);
+        assert_eq!((ours.len(), theirs.len()), (5, 2));
+    }
+
+    #[test]
+    fn test_propose_block_to_empty_chain_fails_cleanly() {
+        let consensus = FixedPeerConsensus::new(vec![Peer::new(1), Peer::new(2), Peer::new(3)]);
+        let mut chain = Blockchain::new().with_block_reward([9; 32], 50);
+        chain.blocks.clear();
+        let txs = vec![dummy_tx([1; 32], [2; 32], 1)];
+
+        assert!(matches!(
+            consensus.try_propose_block(txs.clone(), &mut chain),
+            Err(ChainError::EmptyChain)
+        ));
+        assert!(!consensus.propose_block(txs.clone(), &mut chain));
+        assert!(matches!(
+            chain.try_add_block(txs),
+            Err(ChainError::EmptyChain)
+        ));
+        assert!(chain.is_empty());
+
+        let mut chain = Blockchain::new();
+        assert!(
+            consensus
+                .try_propose_block(vec![dummy_tx([1; 32], [2; 32], 1)], &mut chain)
+                .unwrap()
+        );
+        assert!(
+            !FixedPeerConsensus::new(vec![])
+                .try_propose_block(vec![dummy_tx([1; 32], [2; 32], 2)], &mut chain)
+                .unwrap()
+        );
+        assert_eq!(chain.len(), 2);
+    }
+
+    #[test]
+    fn test_timestamp_overflow_is_reported() {
+        let clock = Arc::new(MockClock::new(u64::MAX - 1));
+        let mut chain = Blockchain::new_with_timestamp(0).with_clock(clock.clone());
+        chain
+            .try_add_block(vec![dummy_tx([1; 32], [2; 32], 1)])
+            .unwrap();
+        assert_eq!(chain.tip().unwrap().timestamp, u64::MAX - 1);
+        chain
+            .try_add_block(vec![dummy_tx([1; 32], [2; 32], 1).with_nonce(1)])
+            .unwrap();
+        assert_eq!(chain.tip().unwrap().timestamp, u64::MAX);
+
+        clock.set(u64::MAX);
+        assert!(matches!(
+            chain.try_add_block(vec![dummy_tx([1; 32], [2; 32], 1).with_nonce(2)]),
+            Err(ChainError::TimestampOverflow { index: 3 })
+        ));
+        assert_eq!(chain.len(), 3);
+        assert!(chain.is_valid());
+
+        chain.config.allow_equal_timestamps = true;
+        chain
+            .try_add_block(vec![dummy_tx([1; 32], [2; 32], 1).with_nonce(2)])
+            .unwrap();
+        assert_eq!(chain.tip().unwrap().timestamp, u64::MAX);
+    }
+
+    #[test]
+    fn test_checkpoints_reject_alternate_chain() {
+        let honest = chain_with_blocks(1, 3, 1);
+        let checkpoint = (2, honest.blocks[2].hash);
+        let mut config = ChainConfig::default();
+        config.checkpoints.push(checkpoint);
+
+        let mut trusted = honest.clone();
+        trusted.config = config.clone();
+        assert!(trusted.validate().is_ok());
+
+        let mut alternate = chain_with_blocks(1, 3, 2);
+        alternate.config = config.clone();
+        assert!(matches!(
+            alternate.validate(),
+            Err(ChainError::CheckpointMismatch { index: 2, expected }) if expected == checkpoint.1
+        ));
+
+        let mut node = Blockchain::with_genesis(honest.blocks[0].clone()).unwrap();
+        node.config = config;
+        assert!(matches!(
+            node.add_blocks(alternate.blocks[1..].to_vec()),
+            Err(ChainError::CheckpointMismatch { index: 2, .. })
+        ));
+        assert_eq!(node.len(), 1);
+        assert!(matches!(
+            node.import_headers(alternate.headers()[1..].to_vec()),
+            Err(ChainError::CheckpointMismatch { index: 2, .. })
+        ));
+        node.add_blocks(honest.blocks[1..].to_vec()).unwrap();
+        assert_eq!(node.len(), 4);
+    }
+
+    #[test]
+    fn test_cached_balances_follow_added_blocks() {
+        let mut chain = Blockchain::new_with_allocation(vec![([1; 32], 100)]);
+        assert_eq!(chain.balances_cached(), &chain.balances());
+        for i in 0..5u64 {
+            let to = [2 + i as u8; 32];
+            chain.add_block(vec![dummy_tx([1; 32], to, 10 + i).with_nonce(i)]);
+            assert_eq!(chain.balances_cached(), &chain.balances());
+        }
+        chain
+            .add_blocks(vec![chain_with_blocks(1, 1, 9).blocks[1].clone()])
+            .unwrap_err();
+        assert_eq!(chain.balances_cached(), &chain.balances());
+        assert_eq!(chain.balances_cached()[&[1; 32]], 100 - 60);
+
+        let longer = {
+            let mut longer = chain.clone();
+            longer.truncate(2);
+            longer.add_block(vec![dummy_tx([1; 32], [9; 32], 1).with_nonce(1)]);
+            for i in 2..8u64 {
+                longer.add_block(vec![dummy_tx([1; 32], [9; 32], 1).with_nonce(i)]);
+            }
+            longer
+        };
+        assert!(chain.try_replace(longer.clone()).unwrap());
+        assert_eq!(chain.balances_cached(), &longer.balances());
+        assert_eq!(chain.balances_cached()[&[9; 32]], 7);
+
+        chain.prune_before(3);
+        assert_eq!(chain.balances_cached(), &chain.balances());
+    }
+
+    #[test]
+    fn test_validate_transactions_checks_candidate_set() {
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let sender = key.verifying_key().to_bytes();
+        let mut chain = Blockchain::new_with_allocation(vec![(sender, 100)])
+            .with_overdraft_protection(true)
+            .with_signature_verification(true);
+        let first = signed_tx(&key, [2; 32], 60);
+        assert!(
+            chain
+                .validate_transactions(std::slice::from_ref(&first))
+                .is_ok()
+        );
+
+        let mut second = Transaction::new(sender, [3; 32], 50).with_nonce(1);
+        second.sign(&key);
+        assert!(matches!(
+            chain.validate_transactions(&[first.clone(), second.clone()]),
+            Err(ChainError::InsufficientFunds {
+                needed: 50,
+                available: 40,
+                ..
+            })
+        ));
+
+        let mut forged = second.clone();
+        forged.amount = 30;
+        assert!(matches!(
+            chain.validate_transactions(&[first.clone(), forged]),
+            Err(ChainError::InvalidSignature {
+                index: 1,
+                position: 1
+            })
+        ));
+        assert_eq!(chain.len(), 1);
+
+        chain.try_add_block(vec![first.clone()]).unwrap();
+        assert!(matches!(
+            chain.validate_transactions(&[first]),
+            Err(ChainError::BadNonce { .. })
+        ));
+    }
+
+    #[test]
+    fn test_hex_dump_annotates_block_fields() {
+        let genesis = create_genesis_block(1, &Sha256Hasher);
+        let dump = genesis.hex_dump();
+        assert!(dump.contains(&hex::encode(genesis.hash)));
+        // 10 заголовков полей и 14 строк по 16 байт (или меньше).
+        assert_eq!(dump.lines().count(), 24);
+        assert!(dump.starts_with("-- index (8 байт) = 0\n00000000  00 00 00 00 00 00 00 00\n"));
+        let last_offset = dump
+            .lines()
+            .last()
+            .unwrap()
+            .split_whitespace()
+            .next()
+            .unwrap();
+        assert_eq!(
+            usize::from_str_radix(last_offset, 16).unwrap() + 16,
+            serialize_block(&genesis).unwrap().len()
+        );
+
+        let mut chain = Blockchain::with_genesis(genesis).unwrap();
+        chain.add_block(vec![dummy_tx([1; 32], [2; 32], 5)]);
+        let tip = chain.tip().unwrap();
+        let dump = tip.hex_dump();
+        assert!(dump.contains("-- transactions (8 байт) = 1 шт."));
+        assert!(dump.contains(&format!(
+            "-- transactions[0] ({} байт) = {}",
+            bincode::serialized_size(&tip.transactions[0]).unwrap(),
+            hex::encode(tip.transactions[0].txid())
+        )));
+    }
+
+    #[test]
+    fn test_check_not_future_rejects_tip_ahead_of_clock() {
+        let mut chain = Blockchain::new_with_timestamp(100 * NANOS_PER_SEC);
+        push_block_at(&mut chain, 200 * NANOS_PER_SEC, 0);
+
+        let clock = MockClock::new(150 * NANOS_PER_SEC);
+        assert!(matches!(
+            chain.check_not_future(&clock, 10 * NANOS_PER_SEC),
+            Err(ChainError::FutureTimestamp { index: 1, timestamp, now })
+                if timestamp == 200 * NANOS_PER_SEC && now == 150 * NANOS_PER_SEC
+        ));
+        assert!(chain.check_not_future(&clock, 50 * NANOS_PER_SEC).is_ok());
+
+        clock.set(200 * NANOS_PER_SEC);
+        assert!(chain.check_not_future(&clock, 0).is_ok());
+        clock.set(u64::MAX);
+        assert!(chain.check_not_future(&clock, 0).is_ok());
+    }
+
+    #[test]
+    fn test_compact_serialization_roundtrips_and_is_smaller() {
+        let mut chain = Blockchain::new_with_timestamp(0);
+        chain.config.max_block_bytes = Some(1 << 20);
+        let alice = SigningKey::from_bytes(&[7; 32]);
+        let bob = [2; 32];
+        for i in 0..50 {
+            let mut tx = Transaction::new(alice.verifying_key().to_bytes(), bob, 10).with_nonce(i);
+            tx.sign(&alice);
+            chain.add_block(vec![tx, dummy_tx([3; 32], bob, 1).with_nonce(i)]);
+        }
+
+        let compact = serialize_blockchain_compact(&chain).unwrap();
+        let plain = serialize_blockchain(&chain).unwrap();
+        assert!(compact.len() < plain.len());
+
+        let restored = deserialize_blockchain_compact(&compact).unwrap();
+        assert_eq!(restored, chain);
+        assert_eq!(serialize_blockchain(&restored).unwrap(), plain);
+        assert_eq!(
+            restored
+                .get_block_by_hash(&chain.blocks[10].hash)
+                .unwrap()
+                .index,
+            10
+        );
+        assert!(restored.is_valid());
+
+        let empty =
+            serialize_blockchain_compact(&Blockchain::from_blocks_unchecked(vec![])).unwrap();
+        assert!(deserialize_blockchain_compact(&empty).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_compact_keeps_pruned_state_and_rejects_forged_boundary() {
+        let mut chain = chain_with_blocks(1, 3, 1);
+        let balances = chain.balances();
+        chain.prune_before(3);
+        let restored =
+            deserialize_blockchain_compact(&serialize_blockchain_compact(&chain).unwrap()).unwrap();
+        assert_eq!(restored.pruned_before(), 3);
+        assert_eq!(restored.balances(), balances);
+        assert!(restored.is_valid());
+
+        // Граница, под которой остались транзакции, не принимается.
+        let mut tampered = chain.clone();
+        tampered.blocks[1].transactions = vec![dummy_tx([9; 32], [7; 32], 1_000_000)];
+        let restored =
+            deserialize_blockchain_compact(&serialize_blockchain_compact(&tampered).unwrap())
+                .unwrap();
+        assert_eq!(restored.pruned_before(), 0);
+        assert!(restored.validate().is_err());
+    }
+
+    #[test]
+    fn test_compact_rejects_unknown_address_index() {
+        let mut chain = Blockchain::new_with_timestamp(0);
+        chain.add_block(vec![dummy_tx([1; 32], [2; 32], 5)]);
+        let mut bytes = serialize_blockchain_compact(&chain).unwrap();
+        // Таблица адресов идёт после версии (2 байта) и своей длины (8 байт);
+        // без второго адреса номер получателя указывает за её пределы.
+        bytes[2] = 1;
+        bytes.drain(10 + 32..10 + 64);
+        assert!(matches!(
+            deserialize_blockchain_compact(&bytes),
+            Err(ChainError::UnknownAddressIndex { index: 1 })
+        ));
+        assert!(deserialize_blockchain_compact(b"garbage").is_err());
+    }
+
+    #[test]
+    fn test_mining_rejects_difficulty_above_hash_width() {
+        let mut block = Block::new(1, 1, vec![], [1; 32]);
+        let hash = block.hash;
+        assert!(matches!(
+            block.mine(MAX_DIFFICULTY as usize + 1),
+            Err(ChainError::DifficultyOutOfRange {
+                difficulty: 257,
+                max: 256
+            })
+        ));
+        assert!(matches!(
+            block.mine(u32::MAX as usize + 1),
+            Err(ChainError::DifficultyOutOfRange { .. })
+        ));
+        assert_eq!((block.hash, block.difficulty), (hash, 0));
+
+        let mut chain = Blockchain::new();
+        assert!(matches!(
+            chain.try_add_block_pow(vec![], 1_000),
+            Err(ChainError::DifficultyOutOfRange {
+                difficulty: 1_000,
+                ..
+            })
+        ));
+        assert_eq!(chain.len(), 1);
+    }
+
+    #[test]
+    fn test_exhausted_nonce_is_reported() {
+        let sender = [1; 32];
+        let mut chain = Blockchain::new();
+        assert_eq!(chain.next_nonce(&sender), Some(0));
+        chain.add_block(vec![dummy_tx(sender, [2; 32], 5).with_nonce(u64::MAX)]);
+        assert_eq!(chain.next_nonce(&sender), None);
+        assert!(matches!(
+            chain.try_add_block(vec![dummy_tx(sender, [2; 32], 5).with_nonce(u64::MAX)]),
+            Err(ChainError::NonceExhausted { addr }) if addr == sender
+        ));
+        assert!(chain.is_valid());
+    }
+}
@@ -0,0 +1,1448 @@
+//! Ядро блокчейна, не зависящее от `std`: транзакции, блоки, хеширование
+//! и дерево Меркла.
+//!
+//! Модуль собирается под `no_std` с `alloc`; системное время и файловый
+//! ввод-вывод доступны только в остальной части крейта (feature `std`).
+
+use ::core::fmt;
+use alloc::string::String;
+use alloc::vec::Vec;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use serde_big_array::BigArray;
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+/// Максимальное количество транзакций в одном блоке по умолчанию
+/// (см. `ChainConfig::max_transactions_per_block`).
+pub const MAX_TRANSACTIONS_PER_BLOCK: usize = 10;
+
+/// Максимально возможная сложность (длина хеша в битах).
+pub const MAX_DIFFICULTY: u32 = 256;
+
+/// Ошибки проверки цепочки.
+///
+/// Варианты, относящиеся к конкретному блоку, содержат его индекс.
+#[derive(Debug)]
+pub enum ChainError {
+    /// Цепочка не содержит ни одного блока.
+    EmptyChain,
+    /// Генезис-блок некорректен (индекс, `previous_hash` или хеш).
+    InvalidGenesis,
+    /// Индекс блока не следует за индексом предыдущего.
+    NonMonotonicIndex { index: u64 },
+    /// `previous_hash` блока не совпадает с хешем предыдущего блока.
+    BrokenLink { index: u64 },
+    /// Сохранённый хеш блока не совпадает с пересчитанным.
+    HashMismatch { index: u64 },
+    /// Хеш блока не удовлетворяет заявленной сложности.
+    InsufficientWork { index: u64 },
+    /// Заявленная сложность блока не совпадает с ожидаемой по правилам перерасчёта.
+    DifficultyMismatch {
+        index: u64,
+        expected: u32,
+        actual: u32,
+    },
+    /// Ошибка ввода-вывода при работе с файлом.
+    #[cfg(feature = "std")]
+    Io(std::io::Error),
+    /// Ошибка сериализации или десериализации.
+    #[cfg(feature = "std")]
+    Serialization(bincode::Error),
+    /// Ошибка кодирования или разбора CBOR.
+    #[cfg(feature = "cbor")]
+    Cbor(String),
+    /// В блоке больше транзакций, чем допускается.
+    TooManyTransactions { count: usize, max: usize },
+    /// Транзакция встречается в блоке повторно (`position` — индекс повтора).
+    DuplicateTransaction { position: usize },
+    /// Корень Меркла блока не совпадает с его транзакциями.
+    MerkleRootMismatch { index: u64 },
+    /// Транзакция с нулевой суммой (`position` — её индекс в блоке).
+    ZeroAmount { position: usize },
+    /// Сумма транзакций блока переполняет `u64`.
+    AmountOverflow,
+    /// Отправитель не имеет достаточных средств для перевода.
+    InsufficientFunds {
+        addr: [u8; 32],
+        needed: u64,
+        available: i128,
+    },
+    /// Подпись транзакции `position` в блоке `index` недействительна.
+    InvalidSignature { index: u64, position: usize },
+    /// Nonce транзакции отправителя не больше последнего использованного.
+    BadNonce {
+        addr: [u8; 32],
+        expected: u64,
+        got: u64,
+    },
+    /// Timestamp блока не больше timestamp предыдущего блока.
+    TimestampNotIncreasing { index: u64 },
+    /// Блок создан раньше, чем через `min` нс после предыдущего.
+    BlockTooSoon { index: u64, interval: u64, min: u64 },
+    /// Блок создан позже, чем через `max` нс после предыдущего.
+    BlockTooLate { index: u64, interval: u64, max: u64 },
+    /// Генезис-блок цепочки отличается от ожидаемого (другая сеть).
+    GenesisMismatch { expected: [u8; 32] },
+    /// Coinbase-транзакция (с `COINBASE_ADDRESS`) не на своём месте: в блоке
+    /// после генезиса допустима только одна, первой транзакцией, и только
+    /// если блоку положена награда или в нём есть комиссии.
+    UnexpectedCoinbase { index: u64, position: usize },
+    /// Сумма coinbase-транзакции блока не совпадает с наградой за блок
+    /// вместе с комиссиями его транзакций.
+    InvalidReward {
+        index: u64,
+        expected: u64,
+        actual: u64,
+    },
+    /// Строка не является адресом: ожидается ровно 64 hex-символа.
+    InvalidAddress { input: String },
+    /// Формат сохранённой цепочки новее, чем поддерживает эта версия.
+    UnsupportedVersion { version: u16 },
+    /// Сериализованный блок больше допустимого размера.
+    BlockTooLarge { index: u64, size: usize, max: usize },
+    /// Отправитель транзакции совпадает с получателем (`position` — её индекс в блоке).
+    SelfTransfer { position: usize },
+    /// Данные для десериализации превышают допустимый объём `limit` байт.
+    SizeLimitExceeded { limit: u64 },
+    /// Для тела блока `index` нет заголовка, импортированного `import_headers`.
+    MissingHeader { index: u64 },
+    /// У блока после генезиса непустые `metadata`.
+    UnexpectedMetadata { index: u64 },
+    /// Транзакция отклонена правилами сети (`ChainConfig::tx_validator`).
+    TransactionRejected { reason: String },
+    /// Фильтр адресов блока не соответствует его транзакциям.
+    AddressFilterMismatch { index: u64 },
+    /// Timestamp блока `index` не помещается в `u64` (предыдущий блок
+    /// уже имеет `u64::MAX`).
+    TimestampOverflow { index: u64 },
+    /// Хеш блока на высоте контрольной точки не совпадает с ожидаемым
+    /// (`ChainConfig::checkpoints`).
+    CheckpointMismatch { index: u64, expected: [u8; 32] },
+    /// Timestamp последнего блока `index` опережает часы `now` больше чем
+    /// на допустимое расхождение (см. `Blockchain::check_not_future`).
+    FutureTimestamp {
+        index: u64,
+        timestamp: u64,
+        now: u64,
+    },
+    /// Номер адреса в компактном формате выходит за пределы таблицы адресов
+    /// (см. `deserialize_blockchain_compact`).
+    UnknownAddressIndex { index: u32 },
+    /// Сложность майнинга больше `MAX_DIFFICULTY`: такой хеш не найти.
+    DifficultyOutOfRange { difficulty: usize, max: u32 },
+    /// Баланс адреса `addr` выходит за пределы `i128` при применении
+    /// блока `index` к снимку (см. `StateSnapshot::apply_block`).
+    BalanceOverflow { index: u64, addr: [u8; 32] },
+    /// Отправитель уже использовал nonce `u64::MAX` и не может отправлять
+    /// новые транзакции.
+    NonceExhausted { addr: [u8; 32] },
+    /// Цепочка-кандидат расходится с текущей до границы удалённой части
+    /// (`index` — последний удалённый блок), поэтому её не проверить.
+    PrunedHistoryMismatch { index: u64 },
+    /// Поток запаниковал, удерживая блокировку `SharedBlockchain` на запись,
+    /// и цепочка могла остаться частично изменённой.
+    LockPoisoned,
+}
+
+impl fmt::Display for ChainError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChainError::EmptyChain => write!(f, "Цепочка не содержит блоков"),
+            ChainError::InvalidGenesis => write!(f, "Некорректный генезис-блок"),
+            ChainError::NonMonotonicIndex { index } => {
+                write!(f, "Блок #{}: нарушена последовательность индексов", index)
+            }
+            ChainError::BrokenLink { index } => {
+                write!(
+                    f,
+                    "Блок #{}: previous_hash не совпадает с хешем предыдущего блока",
+                    index
+                )
+            }
+            ChainError::HashMismatch { index } => {
+                write!(f, "Блок #{}: хеш не совпадает с содержимым", index)
+            }
+            ChainError::InsufficientWork { index } => {
+                write!(
+                    f,
+                    "Блок #{}: хеш не удовлетворяет заявленной сложности",
+                    index
+                )
+            }
+            ChainError::DifficultyMismatch {
+                index,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "Блок #{}: сложность {} вместо ожидаемой {}",
+                index, actual, expected
+            ),
+            #[cfg(feature = "std")]
+            ChainError::Io(e) => write!(f, "Ошибка ввода-вывода: {}", e),
+            #[cfg(feature = "std")]
+            ChainError::Serialization(e) => write!(f, "Ошибка сериализации: {}", e),
+            #[cfg(feature = "cbor")]
+            ChainError::Cbor(e) => write!(f, "Ошибка CBOR: {}", e),
+            ChainError::TooManyTransactions { count, max } => write!(
+                f,
+                "Превышено максимальное число транзакций в блоке: {} > {}",
+                count, max
+            ),
+            ChainError::DuplicateTransaction { position } => {
+                write!(f, "Транзакция #{} повторяется в блоке", position)
+            }
+            ChainError::MerkleRootMismatch { index } => {
+                write!(
+                    f,
+                    "Блок #{}: корень Меркла не совпадает с транзакциями",
+                    index
+                )
+            }
+            ChainError::ZeroAmount { position } => {
+                write!(f, "Транзакция #{} имеет нулевую сумму", position)
+            }
+            ChainError::AmountOverflow => {
+                write!(f, "Сумма транзакций блока переполняет u64")
+            }
+            ChainError::InsufficientFunds {
+                addr,
+                needed,
+                available,
+            } => write!(
+                f,
+                "Недостаточно средств у {}: нужно {}, доступно {}",
+                hex::encode(addr),
+                needed,
+                available
+            ),
+            ChainError::InvalidSignature { index, position } => write!(
+                f,
+                "Блок #{}: недействительная подпись транзакции #{}",
+                index, position
+            ),
+            ChainError::BadNonce {
+                addr,
+                expected,
+                got,
+            } => write!(
+                f,
+                "Некорректный nonce у {}: ожидался не меньше {}, получен {}",
+                hex::encode(addr),
+                expected,
+                got
+            ),
+            ChainError::TimestampNotIncreasing { index } => write!(
+                f,
+                "Блок #{}: timestamp не больше, чем у предыдущего блока",
+                index
+            ),
+            ChainError::BlockTooSoon {
+                index,
+                interval,
+                min,
+            } => write!(
+                f,
+                "Блок #{}: интервал {} нс меньше минимального {} нс",
+                index, interval, min
+            ),
+            ChainError::BlockTooLate {
+                index,
+                interval,
+                max,
+            } => write!(
+                f,
+                "Блок #{}: интервал {} нс больше максимального {} нс",
+                index, interval, max
+            ),
+            ChainError::GenesisMismatch { expected } => write!(
+                f,
+                "Генезис-блок не совпадает с ожидаемым {}: цепочка из другой сети",
+                hex::encode(expected)
+            ),
+            ChainError::UnexpectedCoinbase { index, position } => write!(
+                f,
+                "Блок #{}: недопустимая coinbase-транзакция {}",
+                index, position
+            ),
+            ChainError::InvalidReward {
+                index,
+                expected,
+                actual,
+            } => write!(f, "Блок #{}: награда {} вместо {}", index, actual, expected),
+            ChainError::InvalidAddress { input } => write!(
+                f,
+                "Некорректный адрес {:?}: ожидается 64 hex-символа",
+                input
+            ),
+            ChainError::UnsupportedVersion { version } => {
+                write!(f, "Неподдерживаемая версия формата цепочки: {}", version)
+            }
+            ChainError::BlockTooLarge { index, size, max } => write!(
+                f,
+                "Блок #{}: размер {} байт больше максимального {} байт",
+                index, size, max
+            ),
+            ChainError::SelfTransfer { position } => {
+                write!(f, "Транзакция {}: перевод самому себе", position)
+            }
+            ChainError::SizeLimitExceeded { limit } => {
+                write!(f, "Данные превышают допустимый объём {} байт", limit)
+            }
+            ChainError::MissingHeader { index } => {
+                write!(f, "Блок #{}: нет заголовка для тела блока", index)
+            }
+            ChainError::UnexpectedMetadata { index } => {
+                write!(
+                    f,
+                    "Блок #{}: метаданные допустимы только у генезис-блока",
+                    index
+                )
+            }
+            ChainError::TransactionRejected { reason } => {
+                write!(f, "Транзакция отклонена: {}", reason)
+            }
+            ChainError::AddressFilterMismatch { index } => {
+                write!(
+                    f,
+                    "Блок #{}: фильтр адресов не совпадает с транзакциями",
+                    index
+                )
+            }
+            ChainError::TimestampOverflow { index } => {
+                write!(f, "Блок #{}: timestamp не помещается в u64", index)
+            }
+            ChainError::CheckpointMismatch { index, expected } => {
+                write!(
+                    f,
+                    "Блок #{}: хеш не совпадает с контрольной точкой {}",
+                    index,
+                    hex::encode(expected)
+                )
+            }
+            ChainError::FutureTimestamp {
+                index,
+                timestamp,
+                now,
+            } => {
+                write!(
+                    f,
+                    "Блок #{}: timestamp {} опережает текущее время {}",
+                    index, timestamp, now
+                )
+            }
+            ChainError::UnknownAddressIndex { index } => {
+                write!(f, "Адрес #{} отсутствует в таблице адресов", index)
+            }
+            ChainError::DifficultyOutOfRange { difficulty, max } => {
+                write!(
+                    f,
+                    "Сложность {} вне допустимого диапазона (максимум {})",
+                    difficulty, max
+                )
+            }
+            ChainError::BalanceOverflow { index, addr } => {
+                write!(
+                    f,
+                    "Блок #{}: переполнение баланса адреса {}",
+                    index,
+                    hex::encode(addr)
+                )
+            }
+            ChainError::NonceExhausted { addr } => {
+                write!(f, "Nonce адреса {} исчерпаны", hex::encode(addr))
+            }
+            ChainError::PrunedHistoryMismatch { index } => {
+                write!(
+                    f,
+                    "Блок #{}: цепочка расходится с удалённой частью текущей",
+                    index
+                )
+            }
+            ChainError::LockPoisoned => write!(
+                f,
+                "Блокировка цепочки отравлена: поток запаниковал во время записи"
+            ),
+        }
+    }
+}
+
+impl ::core::error::Error for ChainError {
+    fn source(&self) -> Option<&(dyn ::core::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "std")]
+            ChainError::Io(e) => Some(e),
+            #[cfg(feature = "std")]
+            ChainError::Serialization(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for ChainError {
+    fn from(e: std::io::Error) -> Self {
+        ChainError::Io(e)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<bincode::Error> for ChainError {
+    fn from(e: bincode::Error) -> Self {
+        ChainError::Serialization(e)
+    }
+}
+
+/// Хеш-функция, которой вычисляются хеши блоков.
+///
+/// Смена хеш-функции меняет хеши всех блоков, поэтому цепочка, построенная
+/// с одной функцией, несовместима с узлами, использующими другую. Дерево
+/// Меркла транзакций всегда строится на SHA-256.
+pub trait Hasher: fmt::Debug + Send + Sync {
+    /// Хеш байтов `bytes`.
+    fn hash(&self, bytes: &[u8]) -> [u8; 32];
+}
+
+/// Хеш-функция по умолчанию — SHA-256.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Sha256Hasher;
+
+impl Hasher for Sha256Hasher {
+    fn hash(&self, bytes: &[u8]) -> [u8; 32] {
+        Sha256::digest(bytes).into()
+    }
+}
+
+/// Хеш-функция BLAKE3.
+#[cfg(feature = "blake3")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Blake3Hasher;
+
+#[cfg(feature = "blake3")]
+impl Hasher for Blake3Hasher {
+    fn hash(&self, bytes: &[u8]) -> [u8; 32] {
+        blake3::hash(bytes).into()
+    }
+}
+
+/// Длина заголовка блока в байтах (см. `Block::header_bytes`).
+pub const BLOCK_HEADER_LEN: usize = 8 + 8 + 32 + 32 + 8 + 4;
+
+/// Размер фильтра адресов блока в байтах (см. `Block::address_filter`).
+pub const ADDRESS_FILTER_BYTES: usize = 32;
+
+/// Число бит фильтра, выставляемых для одного адреса.
+const ADDRESS_FILTER_HASHES: usize = 3;
+
+/// Адрес-отправитель coinbase-транзакций, выпускающих новые монеты.
+///
+/// Такие транзакции не подписываются и не списывают средства с отправителя.
+pub const COINBASE_ADDRESS: [u8; 32] = [0u8; 32];
+
+/// Максимальное количество ключей в условии мультиподписи (`MultiSig::keys`).
+pub const MAX_MULTISIG_KEYS: usize = 16;
+
+/// Подпись одного из ключей мультиподписи.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct KeySignature {
+    /// Индекс ключа в `MultiSig::keys`.
+    pub key_index: u8,
+    /// Подпись Ed25519 над `Transaction::signing_bytes`.
+    #[serde(with = "BigArray")]
+    pub signature: [u8; 64],
+}
+
+/// Условие мультиподписи m-of-n: транзакцию должны подписать не менее `m`
+/// разных ключей из `keys`.
+///
+/// Отправитель такой транзакции — адрес условия (`address`), поэтому условие
+/// нельзя подменить, не сменив отправителя.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct MultiSig {
+    /// Необходимое число подписей.
+    pub m: u8,
+    /// Публичные ключи участников (не больше `MAX_MULTISIG_KEYS`).
+    pub keys: Vec<[u8; 32]>,
+    /// Собранные подписи; в `signing_bytes` не входят.
+    pub signatures: Vec<KeySignature>,
+}
+
+impl MultiSig {
+    /// Условие «`m` из `keys`» без подписей.
+    pub fn new(m: u8, keys: Vec<[u8; 32]>) -> Self {
+        Self {
+            m,
+            keys,
+            signatures: Vec::new(),
+        }
+    }
+
+    /// Адрес условия: SHA-256 от `m` и ключей.
+    pub fn address(&self) -> [u8; 32] {
+        Sha256::digest(self.policy_bytes()).into()
+    }
+
+    /// `m` и ключи в представлении bincode.
+    fn policy_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(1 + 8 + 32 * self.keys.len());
+        bytes.push(self.m);
+        bytes.extend_from_slice(&(self.keys.len() as u64).to_le_bytes());
+        for key in &self.keys {
+            bytes.extend_from_slice(key);
+        }
+        bytes
+    }
+
+    /// Корректно ли условие: `1 <= m <= n <= MAX_MULTISIG_KEYS`.
+    pub fn is_well_formed(&self) -> bool {
+        self.m >= 1
+            && usize::from(self.m) <= self.keys.len()
+            && self.keys.len() <= MAX_MULTISIG_KEYS
+    }
+
+    /// Количество разных ключей, чьи подписи над `message` действительны.
+    fn valid_signatures(&self, message: &[u8]) -> usize {
+        let mut signed = 0u32;
+        for entry in &self.signatures {
+            let Some(key) = self.keys.get(usize::from(entry.key_index)) else {
+                continue;
+            };
+            let Ok(key) = VerifyingKey::from_bytes(key) else {
+                continue;
+            };
+            if key
+                .verify(message, &Signature::from_bytes(&entry.signature))
+                .is_ok()
+            {
+                signed |= 1 << entry.key_index;
+            }
+        }
+        signed.count_ones() as usize
+    }
+}
+
+/// Структура транзакции.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct Transaction {
+    /// Отправитель (публичный ключ, 32 байта).
+    pub from: [u8; 32],
+    /// Получатель (публичный ключ, 32 байта).
+    pub to: [u8; 32],
+    /// Сумма в минимальных единицах.
+    pub amount: u64,
+    /// Порядковый номер транзакции отправителя; должен строго возрастать.
+    pub nonce: u64,
+    /// Комиссия, которую отправитель платит сверх `amount`; достаётся
+    /// получателю coinbase-транзакции блока.
+    pub fee: u64,
+    /// Условие мультиподписи; если задано, `from` — адрес условия, а вместо
+    /// `signature` проверяются подписи условия.
+    pub multisig: Option<MultiSig>,
+    /// Подпись Ed25519 отправителя над `signing_bytes` (нули — не подписана).
+    #[serde(with = "BigArray")]
+    pub signature: [u8; 64],
+}
+
+impl Transaction {
+    /// Создание неподписанной транзакции.
+    pub fn new(from: [u8; 32], to: [u8; 32], amount: u64) -> Self {
+        Self {
+            from,
+            to,
+            amount,
+            nonce: 0,
+            fee: 0,
+            multisig: None,
+            signature: [0u8; 64],
+        }
+    }
+
+    /// Создание неподписанной транзакции с общего адреса мультиподписи
+    /// (`from` — `multisig.address()`).
+    pub fn new_multisig(multisig: MultiSig, to: [u8; 32], amount: u64) -> Self {
+        Self {
+            multisig: Some(multisig.clone()),
+            ..Self::new(multisig.address(), to, amount)
+        }
+    }
+
+    /// Создание coinbase-транзакции, выпускающей `amount` монет на адрес `to`.
+    pub fn coinbase(to: [u8; 32], amount: u64) -> Self {
+        Self::new(COINBASE_ADDRESS, to, amount)
+    }
+
+    /// Является ли транзакция coinbase-транзакцией (`from == COINBASE_ADDRESS`).
+    pub fn is_coinbase(&self) -> bool {
+        self.from == COINBASE_ADDRESS
+    }
+
+    /// Задаёт nonce транзакции.
+    pub fn with_nonce(mut self, nonce: u64) -> Self {
+        self.nonce = nonce;
+        self
+    }
+
+    /// Задаёт комиссию транзакции.
+    pub fn with_fee(mut self, fee: u64) -> Self {
+        self.fee = fee;
+        self
+    }
+
+    /// Байты, которые подписывает отправитель: `from`, `to`, `amount`, `nonce`
+    /// и `fee` (в представлении bincode: адреса как есть, числа в little-endian),
+    /// а при мультиподписи — ещё `m` и ключи условия.
+    pub fn signing_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(32 + 32 + 8 + 8 + 8);
+        bytes.extend_from_slice(&self.from);
+        bytes.extend_from_slice(&self.to);
+        bytes.extend_from_slice(&self.amount.to_le_bytes());
+        bytes.extend_from_slice(&self.nonce.to_le_bytes());
+        bytes.extend_from_slice(&self.fee.to_le_bytes());
+        if let Some(multisig) = &self.multisig {
+            bytes.extend_from_slice(&multisig.policy_bytes());
+        }
+        bytes
+    }
+
+    /// Идентификатор транзакции: SHA-256 от `signing_bytes`.
+    ///
+    /// Подпись в идентификатор не входит, поэтому переподписанная транзакция
+    /// сохраняет тот же идентификатор.
+    pub fn txid(&self) -> [u8; 32] {
+        Sha256::digest(self.signing_bytes()).into()
+    }
+
+    /// Подписывает транзакцию ключом отправителя.
+    ///
+    /// Подпись будет действительна, только если `from` совпадает с публичным
+    /// ключом `keypair`.
+    pub fn sign(&mut self, keypair: &SigningKey) {
+        self.signature = keypair.sign(&self.signing_bytes()).to_bytes();
+    }
+
+    /// Добавляет подпись участника мультиподписи, заменяя его прежнюю подпись.
+    ///
+    /// Возвращает `false`, если у транзакции нет условия мультиподписи или
+    /// ключа `keypair` среди его ключей.
+    pub fn cosign(&mut self, keypair: &SigningKey) -> bool {
+        let signature = keypair.sign(&self.signing_bytes()).to_bytes();
+        let public = keypair.verifying_key().to_bytes();
+        let Some(multisig) = &mut self.multisig else {
+            return false;
+        };
+        let Some(key_index) = multisig.keys.iter().position(|key| *key == public) else {
+            return false;
+        };
+        let key_index = key_index as u8;
+        multisig
+            .signatures
+            .retain(|entry| entry.key_index != key_index);
+        multisig.signatures.push(KeySignature {
+            key_index,
+            signature,
+        });
+        true
+    }
+
+    /// Проверка подписи, используя `from` как публичный ключ.
+    ///
+    /// Для транзакции с мультиподписью условие должно быть корректным,
+    /// `from` — его адресом, а действительных подписей разных ключей —
+    /// не меньше `m`.
+    pub fn verify(&self) -> bool {
+        if let Some(multisig) = &self.multisig {
+            return multisig.is_well_formed()
+                && self.from == multisig.address()
+                && multisig.valid_signatures(&self.signing_bytes()) >= usize::from(multisig.m);
+        }
+        let Ok(key) = VerifyingKey::from_bytes(&self.from) else {
+            return false;
+        };
+        let signature = Signature::from_bytes(&self.signature);
+        key.verify(&self.signing_bytes(), &signature).is_ok()
+    }
+}
+
+/// Структура блока.
+///
+/// Каждый блок содержит:
+/// - `index` — порядковый номер,
+/// - `timestamp` — время создания в наносекундах с Unix-эпохи
+///   (`u64` вмещает моменты примерно до 2554 года),
+/// - `transactions` — список транзакций,
+/// - `merkle_root` — корень дерева Меркла транзакций (32 байта),
+/// - `previous_hash` — хеш предыдущего блока (32 байта),
+/// - `nonce` — значение, подбираемое при майнинге,
+/// - `difficulty` — заявленная сложность (число ведущих нулевых бит хеша),
+/// - `hash` — хеш текущего блока (32 байта, SHA-256),
+/// - `metadata` — данные, описывающие сеть (например, её имя); бывают только
+///   у генезис-блока и входят в `merkle_root`, а через него — в хеш,
+/// - `address_filter` — фильтр Блума по адресам отправителей и получателей
+///   (см. `may_contain_address`); в хеш не входит, но сверяется
+///   с транзакциями при проверке блока.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Block {
+    pub index: u64,
+    pub timestamp: u64,
+    pub transactions: Vec<Transaction>,
+    pub merkle_root: [u8; 32],
+    pub previous_hash: [u8; 32],
+    pub nonce: u64,
+    pub difficulty: u32,
+    pub hash: [u8; 32],
+    pub metadata: Vec<u8>,
+    pub address_filter: [u8; ADDRESS_FILTER_BYTES],
+}
+
+impl Block {
+    /// Создание блока с вычисленными корнем Меркла и хешем (без майнинга).
+    pub fn new(
+        index: u64,
+        timestamp: u64,
+        transactions: Vec<Transaction>,
+        previous_hash: [u8; 32],
+    ) -> Self {
+        let mut block = Block {
+            index,
+            timestamp,
+            merkle_root: merkle_root(&transactions),
+            address_filter: address_filter(&transactions),
+            transactions,
+            previous_hash,
+            nonce: 0,
+            difficulty: 0,
+            hash: [0u8; 32],
+            metadata: Vec::new(),
+        };
+        block.hash = block.calculate_hash();
+        block
+    }
+
+    /// Функция вычесления хеша блока на основе его содержимого (исключая поле `hash`)
+    /// функцией SHA-256.
+    pub fn calculate_hash(&self) -> [u8; 32] {
+        self.calculate_hash_with(&Sha256Hasher)
+    }
+
+    /// Вычисление хеша блока заданной хеш-функцией.
+    ///
+    /// Хешируется заголовок блока (см. `header_bytes`); транзакции входят
+    /// в хеш через `merkle_root`.
+    pub fn calculate_hash_with(&self, hasher: &dyn Hasher) -> [u8; 32] {
+        hasher.hash(&self.header_bytes())
+    }
+
+    /// Байты заголовка, от которых вычисляется хеш (см. `BlockHeader::bytes`).
+    pub fn header_bytes(&self) -> [u8; BLOCK_HEADER_LEN] {
+        self.header().bytes()
+    }
+
+    /// Заголовок блока — всё, кроме транзакций.
+    pub fn header(&self) -> BlockHeader {
+        BlockHeader {
+            index: self.index,
+            timestamp: self.timestamp,
+            merkle_root: self.merkle_root,
+            previous_hash: self.previous_hash,
+            nonce: self.nonce,
+            difficulty: self.difficulty,
+            hash: self.hash,
+        }
+    }
+
+    /// Майнинг блока: перебирает `nonce`, пока хеш не будет иметь
+    /// `difficulty` ведущих нулевых бит.
+    ///
+    /// Сложность больше `MAX_DIFFICULTY` отклоняется с
+    /// `ChainError::DifficultyOutOfRange`, блок при этом не меняется.
+    pub fn mine(&mut self, difficulty: usize) -> Result<(), ChainError> {
+        self.mine_with(difficulty, &Sha256Hasher)
+    }
+
+    /// Майнинг блока с заданной хеш-функцией.
+    pub fn mine_with(&mut self, difficulty: usize, hasher: &dyn Hasher) -> Result<(), ChainError> {
+        self.difficulty = u32::try_from(difficulty)
+            .ok()
+            .filter(|&difficulty| difficulty <= MAX_DIFFICULTY)
+            .ok_or(ChainError::DifficultyOutOfRange {
+                difficulty,
+                max: MAX_DIFFICULTY,
+            })?;
+        self.nonce = 0;
+        self.hash = self.calculate_hash_with(hasher);
+        while !self.meets_difficulty() {
+            self.nonce += 1;
+            self.hash = self.calculate_hash_with(hasher);
+        }
+        Ok(())
+    }
+
+    /// Проверка, что сохранённый хеш удовлетворяет заявленной сложности.
+    pub fn meets_difficulty(&self) -> bool {
+        leading_zero_bits(&self.hash) >= self.difficulty
+    }
+
+    /// Проверка блока, полученного извне, относительно его предшественника:
+    /// непрерывность индекса, ссылка `previous_hash`, возрастание timestamp,
+    /// корректность собственного хеша, корня Меркла и заявленной сложности.
+    pub fn verify_against(&self, previous: &Block) -> Result<(), ChainError> {
+        self.verify_link(previous, false)?;
+        self.verify_contents(&Sha256Hasher, true)
+    }
+
+    /// Проверки, связывающие блок с предшественником.
+    ///
+    /// При `allow_equal_timestamps` допускается timestamp, равный предыдущему.
+    pub(crate) fn verify_link(
+        &self,
+        previous: &Block,
+        allow_equal_timestamps: bool,
+    ) -> Result<(), ChainError> {
+        let index = self.index;
+        if index != previous.index + 1 {
+            return Err(ChainError::NonMonotonicIndex { index });
+        }
+        if self.previous_hash != previous.hash {
+            return Err(ChainError::BrokenLink { index });
+        }
+        let increasing = if allow_equal_timestamps {
+            self.timestamp >= previous.timestamp
+        } else {
+            self.timestamp > previous.timestamp
+        };
+        if !increasing {
+            return Err(ChainError::TimestampNotIncreasing { index });
+        }
+        Ok(())
+    }
+
+    /// Проверки, не зависящие от соседних блоков: хеш, корень Меркла, фильтр
+    /// адресов и сложность.
+    ///
+    /// При `check_body == false` (тело блока удалено) корень Меркла и фильтр не
+    /// пересчитываются: блок проверяется по сохранённым `merkle_root` и хешу.
+    pub(crate) fn verify_contents(
+        &self,
+        hasher: &dyn Hasher,
+        check_body: bool,
+    ) -> Result<(), ChainError> {
+        let index = self.index;
+        if index != 0 && !self.metadata.is_empty() {
+            return Err(ChainError::UnexpectedMetadata { index });
+        }
+        if !constant_time_eq(&self.hash, &self.calculate_hash_with(hasher)) {
+            return Err(ChainError::HashMismatch { index });
+        }
+        if check_body && !self.verify_merkle_root() {
+            return Err(ChainError::MerkleRootMismatch { index });
+        }
+        if check_body && !self.verify_address_filter() {
+            return Err(ChainError::AddressFilterMismatch { index });
+        }
+        if !self.meets_difficulty() {
+            return Err(ChainError::InsufficientWork { index });
+        }
+        Ok(())
+    }
+
+    /// Проверка, что `merkle_root` соответствует транзакциям (и метаданным) блока.
+    pub fn verify_merkle_root(&self) -> bool {
+        self.merkle_root == body_root(&self.transactions, &self.metadata)
+    }
+
+    /// Проверка, что `address_filter` соответствует транзакциям блока.
+    pub fn verify_address_filter(&self) -> bool {
+        self.address_filter == address_filter(&self.transactions)
+    }
+
+    /// Может ли блок содержать транзакции с адресом `addr`.
+    ///
+    /// `false` означает, что адреса в блоке точно нет; `true` — что он есть
+    /// или фильтр ложно сработал.
+    pub fn may_contain_address(&self, addr: &[u8; 32]) -> bool {
+        address_filter_bits(addr)
+            .into_iter()
+            .all(|bit| self.address_filter[bit / 8] & (1 << (bit % 8)) != 0)
+    }
+
+    /// Метаданные блока как строка UTF-8 (`None`, если их нет или это не UTF-8).
+    pub fn metadata_str(&self) -> Option<&str> {
+        if self.metadata.is_empty() {
+            return None;
+        }
+        ::core::str::from_utf8(&self.metadata).ok()
+    }
+
+    /// Суммарный объём транзакций блока или `None` при переполнении `u64`.
+    pub fn total_amount(&self) -> Option<u64> {
+        total_amount(&self.transactions)
+    }
+
+    /// Сумма комиссий обычных (не coinbase) транзакций блока.
+    ///
+    /// При переполнении `u64` возвращает `u64::MAX`; такой блок не пройдёт
+    /// проверку при добавлении в цепочку.
+    pub fn total_fees(&self) -> u64 {
+        total_fees(&self.transactions).unwrap_or(u64::MAX)
+    }
+
+    /// Доказательство включения транзакции с позицией `tx_index`: хеши соседних
+    /// узлов от листа к корню. Возвращает `None`, если такой транзакции нет.
+    pub fn merkle_proof(&self, tx_index: usize) -> Option<Vec<[u8; 32]>> {
+        if tx_index >= self.transactions.len() {
+            return None;
+        }
+        let mut level: Vec<[u8; 32]> = self.transactions.iter().map(hash_transaction).collect();
+        let mut position = tx_index;
+        let mut proof = Vec::new();
+        while level.len() > 1 {
+            let sibling = if position.is_multiple_of(2) {
+                // Последний узел нечётного уровня образует пару сам с собой
+                *level.get(position + 1).unwrap_or(&level[position])
+            } else {
+                level[position - 1]
+            };
+            proof.push(sibling);
+            level = next_merkle_level(&level);
+            position /= 2;
+        }
+        Some(proof)
+    }
+}
+
+/// Заголовок блока для лёгких клиентов: все поля блока, кроме транзакций.
+///
+/// Транзакции представлены корнем Меркла, поэтому по заголовкам можно
+/// проверить хеши и связность цепочки, а включение отдельной транзакции —
+/// доказательством Меркла (`verify_merkle_proof`). `nonce` и `difficulty`
+/// нужны, чтобы пересчитать хеш.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockHeader {
+    pub index: u64,
+    pub timestamp: u64,
+    pub merkle_root: [u8; 32],
+    pub previous_hash: [u8; 32],
+    pub nonce: u64,
+    pub difficulty: u32,
+    pub hash: [u8; 32],
+}
+
+impl BlockHeader {
+    /// Байты заголовка, от которых вычисляется хеш блока: поля в порядке
+    /// объявления без `hash`, целые числа — big-endian.
+    ///
+    /// Представление задано явно и не зависит от настроек bincode, которыми
+    /// блоки сериализуются.
+    pub fn bytes(&self) -> [u8; BLOCK_HEADER_LEN] {
+        self.encode(u64::to_be_bytes, u32::to_be_bytes)
+    }
+
+    /// Байты заголовка по правилам форматов до версии 10: те же поля,
+    /// целые числа — little-endian.
+    #[cfg(feature = "std")]
+    pub(crate) fn legacy_bytes(&self) -> [u8; BLOCK_HEADER_LEN] {
+        self.encode(u64::to_le_bytes, u32::to_le_bytes)
+    }
+
+    /// Байты заголовка с целыми числами, закодированными `u64_bytes` и `u32_bytes`.
+    fn encode(
+        &self,
+        u64_bytes: fn(u64) -> [u8; 8],
+        u32_bytes: fn(u32) -> [u8; 4],
+    ) -> [u8; BLOCK_HEADER_LEN] {
+        let mut bytes = [0u8; BLOCK_HEADER_LEN];
+        let fields: [&[u8]; 6] = [
+            &u64_bytes(self.index),
+            &u64_bytes(self.timestamp),
+            &self.merkle_root,
+            &self.previous_hash,
+            &u64_bytes(self.nonce),
+            &u32_bytes(self.difficulty),
+        ];
+        let mut offset = 0;
+        for field in fields {
+            bytes[offset..offset + field.len()].copy_from_slice(field);
+            offset += field.len();
+        }
+        bytes
+    }
+
+    /// Проверка одного заголовка: хеш (SHA-256) и заявленная сложность.
+    pub fn verify(&self) -> bool {
+        constant_time_eq(&self.hash, &Sha256Hasher.hash(&self.bytes()))
+            && leading_zero_bits(&self.hash) >= self.difficulty
+    }
+}
+
+/// Функция проверки цепочки заголовков без транзакций.
+///
+/// Первый заголовок должен быть генезисом (`index == 0`, нулевой
+/// `previous_hash`); у каждого заголовка проверяются хеш и сложность, у
+/// последующих — индекс, ссылка на предыдущий хеш и возрастание timestamp.
+/// Пустой список некорректен.
+pub fn verify_headers(headers: &[BlockHeader]) -> bool {
+    let Some(genesis) = headers.first() else {
+        return false;
+    };
+    genesis.index == 0
+        && genesis.previous_hash == [0u8; 32]
+        && headers.iter().all(BlockHeader::verify)
+        && headers.windows(2).all(|pair| {
+            let (previous, header) = (&pair[0], &pair[1]);
+            header.index == previous.index + 1
+                && header.previous_hash == previous.hash
+                && header.timestamp > previous.timestamp
+        })
+}
+
+/// Человекочитаемое описание блока: индекс, timestamp, хеш, транзакции
+/// и хеш предыдущего блока.
+impl fmt::Display for Block {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "--- Block {} ---", self.index)?;
+        writeln!(f, "Timestamp: {} нс", self.timestamp)?;
+        writeln!(f, "Hash: {}", hex::encode(self.hash))?;
+        writeln!(f, "Transactions:")?;
+        if self.transactions.is_empty() {
+            writeln!(f, "  (нет транзакций)")?;
+        } else {
+            for tx in &self.transactions {
+                writeln!(
+                    f,
+                    "  {} → {} : {}",
+                    hex::encode(tx.from),
+                    hex::encode(tx.to),
+                    tx.amount
+                )?;
+            }
+        }
+        write!(f, "Prev: {}", hex::encode(self.previous_hash))
+    }
+}
+
+/// Функция суммирования транзакций с проверкой переполнения.
+pub(crate) fn total_amount(txs: &[Transaction]) -> Option<u64> {
+    txs.iter()
+        .try_fold(0u64, |sum, tx| sum.checked_add(tx.amount))
+}
+
+/// Сумма комиссий обычных (не coinbase) транзакций или `None` при переполнении `u64`.
+pub(crate) fn total_fees(txs: &[Transaction]) -> Option<u64> {
+    txs.iter()
+        .filter(|tx| !tx.is_coinbase())
+        .try_fold(0u64, |sum, tx| sum.checked_add(tx.fee))
+}
+
+/// Функция хеширования транзакции — лист дерева Меркла.
+///
+/// Поля подаются в хешер напрямую в порядке объявления, целые числа —
+/// big-endian (как в `BlockHeader::bytes`); условие мультиподписи с подписями —
+/// только если оно задано.
+pub(crate) fn hash_transaction(tx: &Transaction) -> [u8; 32] {
+    transaction_digest(tx, u64::to_be_bytes)
+}
+
+/// Хеш транзакции по правилам форматов до версии 10: целые числа —
+/// little-endian.
+#[cfg(feature = "std")]
+fn legacy_hash_transaction(tx: &Transaction) -> [u8; 32] {
+    transaction_digest(tx, u64::to_le_bytes)
+}
+
+/// Хеш транзакции с целыми числами, закодированными `u64_bytes`.
+fn transaction_digest(tx: &Transaction, u64_bytes: fn(u64) -> [u8; 8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(tx.from);
+    hasher.update(tx.to);
+    hasher.update(u64_bytes(tx.amount));
+    hasher.update(u64_bytes(tx.nonce));
+    hasher.update(u64_bytes(tx.fee));
+    if let Some(multisig) = &tx.multisig {
+        hasher.update([multisig.m]);
+        hasher.update(u64_bytes(multisig.keys.len() as u64));
+        for key in &multisig.keys {
+            hasher.update(key);
+        }
+        hasher.update(u64_bytes(multisig.signatures.len() as u64));
+        for entry in &multisig.signatures {
+            hasher.update([entry.key_index]);
+            hasher.update(entry.signature);
+        }
+    }
+    hasher.update(tx.signature);
+    hasher.finalize().into()
+}
+
+/// Значение `merkle_root` блока: корень Меркла транзакций, а при непустых
+/// метаданных — хеш пары из него и SHA-256 метаданных.
+///
+/// Доказательства включения (`merkle_proof`) строятся от корня транзакций,
+/// поэтому для генезис-блока с метаданными с `merkle_root` не сходятся.
+pub(crate) fn body_root(txs: &[Transaction], metadata: &[u8]) -> [u8; 32] {
+    with_metadata(merkle_root(txs), metadata)
+}
+
+/// Значение `merkle_root` по правилам форматов до версии 10 (листья —
+/// `legacy_hash_transaction`).
+#[cfg(feature = "std")]
+pub(crate) fn legacy_body_root(txs: &[Transaction], metadata: &[u8]) -> [u8; 32] {
+    with_metadata(
+        root_of(txs.iter().map(legacy_hash_transaction).collect()),
+        metadata,
+    )
+}
+
+/// Корень транзакций `root` с учётом метаданных блока (см. `body_root`).
+fn with_metadata(root: [u8; 32], metadata: &[u8]) -> [u8; 32] {
+    if metadata.is_empty() {
+        return root;
+    }
+    hash_pair(&root, &Sha256::digest(metadata).into())
+}
+
+/// Фильтр Блума по адресам отправителей и получателей транзакций.
+pub(crate) fn address_filter(txs: &[Transaction]) -> [u8; ADDRESS_FILTER_BYTES] {
+    let mut filter = [0u8; ADDRESS_FILTER_BYTES];
+    for tx in txs {
+        add_to_address_filter(&mut filter, tx);
+    }
+    filter
+}
+
+/// Добавление адресов транзакции в фильтр.
+fn add_to_address_filter(filter: &mut [u8; ADDRESS_FILTER_BYTES], tx: &Transaction) {
+    for addr in [&tx.from, &tx.to] {
+        for bit in address_filter_bits(addr) {
+            filter[bit / 8] |= 1 << (bit % 8);
+        }
+    }
+}
+
+/// Номера бит фильтра для адреса: по два байта SHA-256 адреса на бит.
+fn address_filter_bits(addr: &[u8; 32]) -> [usize; ADDRESS_FILTER_HASHES] {
+    let digest = Sha256::digest(addr);
+    ::core::array::from_fn(|i| {
+        usize::from(u16::from_be_bytes([digest[2 * i], digest[2 * i + 1]]))
+            % (ADDRESS_FILTER_BYTES * 8)
+    })
+}
+
+/// Функция хеширования пары узлов дерева Меркла.
+pub(crate) fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// Функция вычисления корня дерева Меркла для списка транзакций.
+///
+/// Узлы хешируются попарно SHA-256; при нечётном количестве узлов на уровне
+/// последний дублируется. Для пустого списка корень равен `[0u8; 32]`.
+pub fn merkle_root(txs: &[Transaction]) -> [u8; 32] {
+    root_of(txs.iter().map(hash_transaction).collect())
+}
+
+/// Корень дерева Меркла над листьями `leaves` (см. `merkle_root`).
+fn root_of(mut level: Vec<[u8; 32]>) -> [u8; 32] {
+    if level.is_empty() {
+        return [0u8; 32];
+    }
+    while level.len() > 1 {
+        level = next_merkle_level(&level);
+    }
+    level[0]
+}
+
+/// Функция построения следующего уровня дерева Меркла.
+fn next_merkle_level(level: &[[u8; 32]]) -> Vec<[u8; 32]> {
+    level
+        .chunks(2)
+        .map(|pair| hash_pair(&pair[0], pair.get(1).unwrap_or(&pair[0])))
+        .collect()
+}
+
+/// Функция проверки доказательства включения транзакции в блок.
+///
+/// `proof` — хеши соседних узлов от листа к корню (см. `Block::merkle_proof`),
+/// `index` — позиция транзакции в блоке.
+pub fn verify_merkle_proof(
+    tx: &Transaction,
+    proof: &[[u8; 32]],
+    root: [u8; 32],
+    index: usize,
+) -> bool {
+    let mut hash = hash_transaction(tx);
+    let mut position = index;
+    for sibling in proof {
+        hash = if position.is_multiple_of(2) {
+            hash_pair(&hash, sibling)
+        } else {
+            hash_pair(sibling, &hash)
+        };
+        position /= 2;
+    }
+    hash == root
+}
+
+/// Функция разбора 32-байтного адреса из hex-строки (ровно 64 символа).
+pub fn parse_address(s: &str) -> Result<[u8; 32], ChainError> {
+    let mut addr = [0u8; 32];
+    hex::decode_to_slice(s, &mut addr)
+        .map_err(|_| ChainError::InvalidAddress { input: s.into() })?;
+    Ok(addr)
+}
+
+/// Функция представления адреса в виде hex-строки (64 символа).
+pub fn address_to_hex(addr: &[u8; 32]) -> String {
+    hex::encode(addr)
+}
+
+/// Поиск ключевой пары, адрес которой в hex начинается с `prefix` (регистр
+/// не важен). Возвращает секретный ключ и адрес.
+///
+/// Секретные ключи перебираются детерминированно (SHA-256 номера попытки),
+/// поэтому результат воспроизводим, но ключи известны всем: функция годится
+/// только для демонстраций и тестовых данных. Каждый hex-символ префикса
+/// увеличивает ожидаемое число попыток в 16 раз. Возвращает `None`, если
+/// `prefix` не может быть началом адреса или за `max_attempts` попыток
+/// подходящий адрес не найден.
+pub fn generate_vanity_address(prefix: &str, max_attempts: u64) -> Option<([u8; 32], [u8; 32])> {
+    let nibbles: Vec<u8> = prefix
+        .chars()
+        .map(|c| c.to_digit(16).map(|digit| digit as u8))
+        .collect::<Option<_>>()?;
+    if nibbles.len() > 64 {
+        return None;
+    }
+    (0..max_attempts).find_map(|attempt| {
+        let secret: [u8; 32] = Sha256::digest(attempt.to_le_bytes()).into();
+        let address = SigningKey::from_bytes(&secret).verifying_key().to_bytes();
+        let matches = nibbles.iter().enumerate().all(|(i, &nibble)| {
+            let byte = address[i / 2];
+            let digit = if i % 2 == 0 { byte >> 4 } else { byte & 0x0f };
+            digit == nibble
+        });
+        matches.then_some((secret, address))
+    })
+}
+
+/// Сравнение хешей за время, не зависящее от содержимого (через `subtle`).
+///
+/// В отличие от `==`, не завершается на первом несовпавшем байте, поэтому
+/// по времени сравнения нельзя подобрать ожидаемый хеш.
+pub fn constant_time_eq(a: &[u8; 32], b: &[u8; 32]) -> bool {
+    a.ct_eq(b).into()
+}
+
+/// Функция подсчёта ведущих нулевых бит хеша.
+pub fn leading_zero_bits(hash: &[u8; 32]) -> u32 {
+    let mut bits = 0;
+    for byte in hash {
+        bits += byte.leading_zeros();
+        if *byte != 0 {
+            break;
+        }
+    }
+    bits
+}
+
+/// Количество наносекунд в секунде.
+pub const NANOS_PER_SEC: u64 = 1_000_000_000;
+
+/// Функция создания нового блока на основе предыдущего.
+///
+/// Текущее время `now` передаётся вызывающим кодом, поэтому модуль не зависит
+/// от системных часов. Если часы не ушли вперёд относительно предыдущего блока
+/// (быстрая машина, грубый таймер), timestamp сдвигается до минимально
+/// допустимого: на 1 нс больше предыдущего или равного ему при
+/// `allow_equal_timestamps`. Хеш блока вычисляется функцией `hasher`.
+///
+/// Если у предыдущего блока timestamp `u64::MAX` и равные timestamp
+/// запрещены, возвращается `ChainError::TimestampOverflow`.
+pub fn create_block(
+    transactions: Vec<Transaction>,
+    previous_block: &Block,
+    now: u64,
+    allow_equal_timestamps: bool,
+    hasher: &dyn Hasher,
+) -> Result<Block, ChainError> {
+    let index = previous_block.index + 1;
+    let min_timestamp = if allow_equal_timestamps {
+        previous_block.timestamp
+    } else {
+        previous_block
+            .timestamp
+            .checked_add(1)
+            .ok_or(ChainError::TimestampOverflow { index })?
+    };
+    let timestamp = now.max(min_timestamp);
+
+    let mut block = Block::new(index, timestamp, transactions, previous_block.hash);
+    block.hash = block.calculate_hash_with(hasher);
+    Ok(block)
+}
+
+/// Построитель блока, поддерживающий корень Меркла по мере добавления
+/// транзакций.
+///
+/// Для каждого уровня дерева хранится не более одного полного поддерева
+/// («вершина»), как разряды двоичного счётчика, поэтому `add_transaction`
+/// стоит амортизированно O(1) хеширований, а `merkle_root` и `build` —
+/// O(log n). Итоговый корень совпадает с `merkle_root` для тех же транзакций.
+#[derive(Debug, Clone)]
+pub struct BlockBuilder {
+    index: u64,
+    previous_hash: [u8; 32],
+    transactions: Vec<Transaction>,
+    /// `peaks[h]` — корень полного поддерева из `2^h` транзакций, ещё
+    /// не объединённого с соседним.
+    peaks: Vec<Option<[u8; 32]>>,
+    address_filter: [u8; ADDRESS_FILTER_BYTES],
+}
+
+impl BlockBuilder {
+    /// Построитель блока, следующего за `previous_block`.
+    pub fn new(previous_block: &Block) -> Self {
+        Self {
+            index: previous_block.index + 1,
+            previous_hash: previous_block.hash,
+            transactions: Vec::new(),
+            peaks: Vec::new(),
+            address_filter: [0u8; ADDRESS_FILTER_BYTES],
+        }
+    }
+
+    /// Добавляет транзакцию в конец блока.
+    pub fn add_transaction(&mut self, tx: Transaction) {
+        let mut node = hash_transaction(&tx);
+        let mut height = 0;
+        while let Some(peak) = self.peaks.get_mut(height).and_then(Option::take) {
+            node = hash_pair(&peak, &node);
+            height += 1;
+        }
+        if height == self.peaks.len() {
+            self.peaks.push(Some(node));
+        } else {
+            self.peaks[height] = Some(node);
+        }
+        add_to_address_filter(&mut self.address_filter, &tx);
+        self.transactions.push(tx);
+    }
+
+    /// Количество добавленных транзакций.
+    pub fn len(&self) -> usize {
+        self.transactions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.transactions.is_empty()
+    }
+
+    /// Корень Меркла добавленных транзакций.
+    ///
+    /// Вершины объединяются снизу вверх; незавершённый правый узел уровня,
+    /// которому нет пары, дублируется, как в `merkle_root`.
+    pub fn merkle_root(&self) -> [u8; 32] {
+        let count = self.transactions.len();
+        if count == 0 {
+            return [0u8; 32];
+        }
+        let mut tail: Option<[u8; 32]> = None;
+        for (height, peak) in self.peaks.iter().enumerate() {
+            if count.div_ceil(1 << height) == 1 {
+                break;
+            }
+            tail = match (peak, tail) {
+                (Some(peak), Some(tail)) => Some(hash_pair(peak, &tail)),
+                (Some(peak), None) => Some(hash_pair(peak, peak)),
+                (None, Some(tail)) => Some(hash_pair(&tail, &tail)),
+                (None, None) => None,
+            };
+        }
+        tail.or_else(|| self.peaks.last().copied().flatten())
+            .expect("у непустого построителя есть вершина")
+    }
+
+    /// Собирает блок с timestamp `timestamp` и хешем SHA-256 (без майнинга).
+    pub fn build(self, timestamp: u64) -> Block {
+        self.build_with(timestamp, &Sha256Hasher)
+    }
+
+    /// Собирает блок, вычисляя хеш функцией `hasher`.
+    pub fn build_with(self, timestamp: u64, hasher: &dyn Hasher) -> Block {
+        let mut block = Block {
+            index: self.index,
+            timestamp,
+            merkle_root: self.merkle_root(),
+            transactions: self.transactions,
+            previous_hash: self.previous_hash,
+            nonce: 0,
+            difficulty: 0,
+            hash: [0u8; 32],
+            metadata: Vec::new(),
+            address_filter: self.address_filter,
+        };
+        block.hash = block.calculate_hash_with(hasher);
+        block
+    }
+}
+
+/// Функция создания генезиз-блока.
+///
+/// Генезис-блок определяется как блок с `index == 0` и `previous_hash == [0u8; 32]` и не содержит транзакций.
+pub fn create_genesis_block(timestamp: u64, hasher: &dyn Hasher) -> Block {
+    create_genesis_block_with_allocation(timestamp, &[], hasher)
+}
+
+/// Функция создания генезис-блока с начальным распределением монет.
+///
+/// Каждая пара `(адрес, сумма)` превращается в coinbase-транзакцию;
+/// nonce транзакции равен её позиции, чтобы одинаковые пары не совпадали.
+pub fn create_genesis_block_with_allocation(
+    timestamp: u64,
+    allocations: &[([u8; 32], u64)],
+    hasher: &dyn Hasher,
+) -> Block {
+    let transactions = allocations
+        .iter()
+        .zip(0..)
+        .map(|(&(to, amount), nonce)| Transaction::coinbase(to, amount).with_nonce(nonce))
+        .collect();
+    let mut block = Block::new(0, timestamp, transactions, [0u8; 32]);
+    block.hash = block.calculate_hash_with(hasher);
+    block
+}
+
+/// Функция создания генезис-блока с метаданными `metadata` (например,
+/// именем сети).
+///
+/// Метаданные входят в `merkle_root`, поэтому генезис-блоки с разными
+/// метаданными и одинаковым timestamp имеют разные хеши.
+pub fn create_genesis_block_with_metadata(
+    timestamp: u64,
+    metadata: &[u8],
+    hasher: &dyn Hasher,
+) -> Block {
+    let mut block = create_genesis_block(timestamp, hasher);
+    block.metadata = metadata.to_vec();
+    block.merkle_root = body_root(&block.transactions, &block.metadata);
+    block.hash = block.calculate_hash_with(hasher);
+    block
+}
+
+/// Функция проверки, что блок может служить генезис-блоком
+/// (хеш проверяется функцией `hasher`).
+///
+/// Генезис-блок может содержать только coinbase-транзакции.
+pub fn check_genesis(block: &Block, hasher: &dyn Hasher) -> Result<(), ChainError> {
+    if block.index != 0
+        || block.previous_hash != [0u8; 32]
+        || !constant_time_eq(&block.hash, &block.calculate_hash_with(hasher))
+        || !block.transactions.iter().all(Transaction::is_coinbase)
+    {
+        return Err(ChainError::InvalidGenesis);
+    }
+    Ok(())
+}
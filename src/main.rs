@@ -1,18 +1,139 @@
-//! Демонстрация работы блокчейна: создание цепочки, консенсус, валидация, сериализация.
+//! Утилита командной строки для работы с блокчейном, хранящимся в файле.
+//!
+//! Подкоманды: `new`, `add`, `validate`, `print`, `stats` и `demo`
+//! (демонстрация: создание цепочки, консенсус, валидация, сериализация).
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand};
 
 // Импорт компонентов из библиотеки
 use rustblockchain::{
     Blockchain,             // Основная структура блокчейна
+    ChainError,             // Ошибки проверки цепочки
+    Consensus,              // Общий интерфейс механизмов консенсуса
     FixedPeerConsensus,     // Механизм консенсуса
     Peer,                   // Участник пиринговой сети
     Transaction,            // Структура транзакции
+    address_to_hex,         // Адрес в виде hex-строки
     deserialize_block,      // Функция десериализации блока
     deserialize_blockchain, // Функция десериализации блокчейна
+    parse_address,          // Разбор адреса из hex-строки
     serialize_block,        // Функция сериализации блока
     serialize_blockchain,   // Функция сериализации блокчейна
 };
 
-fn main() {
+/// Работа с блокчейном, сохранённым в файле.
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Создать новую цепочку с генезис-блоком и сохранить её в файл.
+    New { path: PathBuf },
+    /// Добавить в цепочку блок из транзакций вида `FROM:TO:AMOUNT`
+    /// (адреса — 32 байта в hex).
+    Add {
+        path: PathBuf,
+        #[arg(required = true, value_parser = parse_transaction)]
+        transactions: Vec<Transaction>,
+    },
+    /// Загрузить цепочку и проверить её целостность.
+    Validate { path: PathBuf },
+    /// Вывести все блоки цепочки.
+    Print { path: PathBuf },
+    /// Вывести статистику цепочки.
+    Stats { path: PathBuf },
+    /// Запустить демонстрацию работы блокчейна.
+    Demo,
+}
+
+/// Разбор транзакции из строки `FROM:TO:AMOUNT`.
+fn parse_transaction(s: &str) -> Result<Transaction, String> {
+    let parts: Vec<&str> = s.split(':').collect();
+    let [from, to, amount] = parts[..] else {
+        return Err(format!("ожидалось FROM:TO:AMOUNT, получено {s:?}"));
+    };
+    let amount = amount
+        .parse()
+        .map_err(|e| format!("некорректная сумма {amount:?}: {e}"))?;
+    Ok(Transaction::new(
+        parse_address(from).map_err(|e| e.to_string())?,
+        parse_address(to).map_err(|e| e.to_string())?,
+        amount,
+    ))
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli.command) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Ошибка: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(command: Command) -> Result<(), ChainError> {
+    match command {
+        Command::New { path } => {
+            let blockchain = Blockchain::new();
+            blockchain.save_to_file(&path)?;
+            println!("Блокчейн создан: {}", path.display());
+        }
+        Command::Add {
+            path,
+            mut transactions,
+        } => {
+            let mut blockchain = Blockchain::load_from_file(&path)?;
+            // Nonce назначаются по порядку для каждого отправителя
+            let mut nonces = HashMap::new();
+            for tx in &mut transactions {
+                let next = *nonces
+                    .entry(tx.from)
+                    .or_insert_with(|| blockchain.next_nonce(&tx.from));
+                tx.nonce = next.ok_or(ChainError::NonceExhausted { addr: tx.from })?;
+                nonces.insert(tx.from, tx.nonce.checked_add(1));
+            }
+            blockchain.try_add_block(transactions)?;
+            blockchain.save_to_file(&path)?;
+            println!(
+                "Блок #{} добавлен.",
+                blockchain.height().unwrap_or_default()
+            );
+        }
+        Command::Validate { path } => {
+            let blockchain = Blockchain::load_from_file(&path)?;
+            println!("Цепочка валидна ({} блоков).", blockchain.len());
+        }
+        Command::Print { path } => Blockchain::load_from_file(&path)?.print_chain(),
+        Command::Stats { path } => {
+            let stats = Blockchain::load_from_file(&path)?.stats();
+            println!("• Всего блоков: {}", stats.block_count);
+            println!("• Всего транзакций: {}", stats.transaction_count);
+            println!("• Общая сумма переводов: {}", stats.total_amount);
+            println!(
+                "  Средний размер блока: {} байт",
+                stats.average_block_size_bytes
+            );
+            if let Some(hash) = stats.last_hash {
+                println!("  Последний хеш: {}", hex::encode(hash));
+            }
+        }
+        Command::Demo => demo(),
+    }
+    Ok(())
+}
+
+/// Демонстрация работы блокчейна: создание цепочки, консенсус, валидация, сериализация.
+fn demo() {
     // 1: Инициализация блокчейна
     println!("Запуск демонстрации блокчейна...\n");
     // Создаём новую цепочку с генезис-блоком
@@ -37,50 +158,18 @@ fn main() {
     // Формируем несколько пакетов транзакций (каждый пакет - один блок)
     let transaction_batches = vec![
         vec![
-            Transaction {
-                from: [1; 32],
-                to: [2; 32],
-                amount: 52,
-            },
-            Transaction {
-                from: [3; 32],
-                to: [4; 32],
-                amount: 69,
-            },
+            Transaction::new([1; 32], [2; 32], 52),
+            Transaction::new([3; 32], [4; 32], 69),
         ],
-        vec![Transaction {
-            from: [5; 32],
-            to: [6; 32],
-            amount: 111,
-        }],
+        vec![Transaction::new([5; 32], [6; 32], 111)],
         vec![
-            Transaction {
-                from: [7; 32],
-                to: [8; 32],
-                amount: 25,
-            },
-            Transaction {
-                from: [9; 32],
-                to: [10; 32],
-                amount: 90,
-            },
+            Transaction::new([7; 32], [8; 32], 25),
+            Transaction::new([9; 32], [10; 32], 90),
         ],
-        vec![Transaction {
-            from: [11; 32],
-            to: [12; 32],
-            amount: 11,
-        }],
+        vec![Transaction::new([11; 32], [12; 32], 11)],
         vec![
-            Transaction {
-                from: [13; 32],
-                to: [14; 32],
-                amount: 250,
-            },
-            Transaction {
-                from: [15; 32],
-                to: [16; 32],
-                amount: 159,
-            },
+            Transaction::new([13; 32], [14; 32], 250),
+            Transaction::new([15; 32], [16; 32], 159),
         ],
     ];
 
@@ -113,30 +202,17 @@ fn main() {
     println!();
 
     // 6: Генерация отчёта о сети
-    // Рассчитываем суммарный и средний размер блоков
-    let serialized_total: usize = blockchain
-        .blocks
-        .iter()
-        .map(|block| serialize_block(block).unwrap().len())
-        .sum();
-    let block_count = blockchain.blocks.len();
-    let average_size = if block_count > 0 {
-        serialized_total / block_count
-    } else {
-        0
-    };
+    // Собираем статистику: количество блоков, транзакций и средний размер блока
+    let stats = blockchain.stats();
     // Вывод статистики
     println!("Отчёт о сети:");
-    println!("• Всего блоков: {}", block_count);
+    println!("• Всего блоков: {}", stats.block_count);
+    println!("• Всего транзакций: {}", stats.transaction_count);
+    println!("• Общая сумма переводов: {}", stats.total_amount);
     println!(
-        "• Всего транзакций: {}",
-        blockchain
-            .blocks
-            .iter()
-            .map(|b| b.transactions.len())
-            .sum::<usize>()
+        "  Средний размер блока: {} байт",
+        stats.average_block_size_bytes
     );
-    println!("  Средний размер блока: {} байт", average_size);
 
     // 7. Поиск блока по индексу
     println!("\nПоиск блока по индексу...");
@@ -148,8 +224,8 @@ fn main() {
         for tx in &block.transactions {
             println!(
                 "     • {} → {} : {}",
-                hex::encode(tx.from),
-                hex::encode(tx.to),
+                address_to_hex(&tx.from),
+                address_to_hex(&tx.to),
                 tx.amount
             );
         }
@@ -160,7 +236,7 @@ fn main() {
     // 8: Сериализация и десериализация (bincode)
     // Один блок
     println!("\nСериализация последнего блока через bincode...");
-    if let Some(last_block) = blockchain.blocks.last() {
+    if let Some(last_block) = blockchain.iter().next_back() {
         // Сериализуем блок в вектор байтов
         match serialize_block(last_block) {
             Ok(encoded) => {
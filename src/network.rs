@@ -0,0 +1,199 @@
+//! Распространение блоков между репликами цепочки.
+
+use crate::chain::Blockchain;
+use crate::core::Block;
+use log::debug;
+use std::collections::{HashMap, VecDeque};
+
+/// Сеть из нескольких реплик цепочки, между которыми рассылаются блоки.
+#[derive(Debug, Default)]
+pub struct Network {
+    pub replicas: Vec<Blockchain>,
+}
+
+impl Network {
+    pub fn new(replicas: Vec<Blockchain>) -> Self {
+        Self { replicas }
+    }
+
+    /// Рассылает блок всем репликам.
+    ///
+    /// Реплика принимает блок, только если он корректно продолжает её цепочку
+    /// (см. `Blockchain::add_blocks`); отставшие реплики и реплики на другой
+    /// ветке его отклоняют. Возвращает число принявших реплик.
+    pub fn broadcast_block(&mut self, block: Block) -> usize {
+        let mut accepted = 0;
+        for (i, replica) in self.replicas.iter_mut().enumerate() {
+            match replica.add_blocks(vec![block.clone()]) {
+                Ok(()) => accepted += 1,
+                Err(e) => debug!("Реплика {} отклонила блок #{}: {}", i, block.index, e),
+            }
+        }
+        debug!(
+            "Блок #{} принят {} из {} реплик",
+            block.index,
+            accepted,
+            self.replicas.len()
+        );
+        accepted
+    }
+}
+
+/// Блоки, пришедшие раньше своих родителей.
+///
+/// Блоки хранятся по `previous_hash`, чтобы после прихода родителя
+/// их можно было присоединить методом `Blockchain::connect_orphans`.
+///
+/// Размер пула ограничен: при переполнении удаляются блоки, пришедшие
+/// раньше остальных.
+#[derive(Debug)]
+pub struct OrphanPool {
+    by_parent: HashMap<[u8; 32], Vec<Block>>,
+    /// `previous_hash` и хеш блоков в порядке поступления.
+    arrival: VecDeque<([u8; 32], [u8; 32])>,
+    capacity: usize,
+}
+
+impl Default for OrphanPool {
+    fn default() -> Self {
+        Self::with_capacity(Self::DEFAULT_CAPACITY)
+    }
+}
+
+impl OrphanPool {
+    /// Ёмкость пула, создаваемого `new`.
+    pub const DEFAULT_CAPACITY: usize = 1024;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Пул, хранящий не больше `capacity` блоков.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            by_parent: HashMap::new(),
+            arrival: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    /// Добавляет блок, родитель которого ещё не получен.
+    ///
+    /// Повторно полученный блок игнорируется. Если пул переполнен,
+    /// из него удаляются самые старые блоки.
+    pub fn insert(&mut self, block: Block) {
+        if self.arrival.iter().any(|(_, hash)| *hash == block.hash) {
+            return;
+        }
+        self.arrival.push_back((block.previous_hash, block.hash));
+        self.by_parent
+            .entry(block.previous_hash)
+            .or_default()
+            .push(block);
+        while self.arrival.len() > self.capacity {
+            let Some((parent, hash)) = self.arrival.pop_front() else {
+                break;
+            };
+            if let Some(children) = self.by_parent.get_mut(&parent) {
+                children.retain(|child| child.hash != hash);
+                if children.is_empty() {
+                    self.by_parent.remove(&parent);
+                }
+            }
+            debug!("Пул сирот переполнен, удалён самый старый блок");
+        }
+    }
+
+    /// Количество блоков в пуле.
+    pub fn len(&self) -> usize {
+        self.arrival.len()
+    }
+
+    /// Максимальное количество блоков в пуле.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Проверка, что пул пуст.
+    pub fn is_empty(&self) -> bool {
+        self.by_parent.is_empty()
+    }
+
+    /// Извлекает из пула все блоки с родителем `parent`.
+    pub(crate) fn take_children(&mut self, parent: &[u8; 32]) -> Vec<Block> {
+        self.arrival.retain(|(previous, _)| previous != parent);
+        self.by_parent.remove(parent).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ChainBuilder;
+
+    #[test]
+    fn test_forked_and_lagging_replicas_reject_block() {
+        let common = || ChainBuilder::new().transfer([1; 32], [2; 32], 1);
+        let mut network = Network::new(vec![
+            common().build(),
+            ChainBuilder::new().transfer([1; 32], [3; 32], 1).build(),
+            common().build(),
+        ]);
+        let block = common().transfer([1; 32], [2; 32], 2).build().blocks[2].clone();
+
+        assert_eq!(network.broadcast_block(block.clone()), 2);
+        assert_eq!(network.replicas[0].blocks[2], block);
+        assert_eq!(network.replicas[1].len(), 2);
+        assert_eq!(network.replicas[2].blocks[2], block);
+        assert!(network.replicas.iter().all(Blockchain::is_valid));
+
+        network.replicas.push(ChainBuilder::new().build());
+        assert_eq!(network.broadcast_block(block), 0);
+        assert_eq!(network.replicas[3].len(), 1);
+    }
+
+    #[test]
+    fn test_orphans_connect_after_parent_arrives() {
+        let reference = ChainBuilder::new()
+            .transfer([1; 32], [2; 32], 1)
+            .transfer([1; 32], [2; 32], 2)
+            .transfer([1; 32], [2; 32], 3)
+            .build();
+        let mut chain = ChainBuilder::new().build();
+        let mut pool = OrphanPool::new();
+        pool.insert(reference.blocks[3].clone());
+        pool.insert(reference.blocks[2].clone());
+        assert_eq!(chain.connect_orphans(&mut pool), 0);
+        assert_eq!(pool.len(), 2);
+
+        chain.add_blocks(vec![reference.blocks[1].clone()]).unwrap();
+        assert_eq!(chain.connect_orphans(&mut pool), 2);
+        assert!(pool.is_empty());
+        assert_eq!(chain, reference);
+    }
+
+    #[test]
+    fn test_orphan_pool_evicts_oldest_blocks() {
+        let reference = ChainBuilder::new()
+            .transfer([1; 32], [2; 32], 1)
+            .transfer([1; 32], [2; 32], 2)
+            .transfer([1; 32], [2; 32], 3)
+            .build();
+        let mut pool = OrphanPool::with_capacity(2);
+        assert_eq!(pool.capacity(), 2);
+        pool.insert(reference.blocks[1].clone());
+        pool.insert(reference.blocks[2].clone());
+        pool.insert(reference.blocks[2].clone());
+        assert_eq!(pool.len(), 2);
+
+        pool.insert(reference.blocks[3].clone());
+        assert_eq!(pool.len(), 2);
+        assert!(pool.take_children(&reference.blocks[0].hash).is_empty());
+        assert_eq!(
+            pool.take_children(&reference.blocks[1].hash),
+            vec![reference.blocks[2].clone()]
+        );
+        assert_eq!(pool.len(), 1);
+        assert_eq!(OrphanPool::new().capacity(), OrphanPool::DEFAULT_CAPACITY);
+    }
+}
@@ -0,0 +1,190 @@
+//! Цепочка с общим доступом из нескольких потоков.
+
+use crate::chain::Blockchain;
+use crate::core::{Block, ChainError, Transaction};
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// Цепочка за `Arc<RwLock<_>>`: много читателей или один писатель.
+///
+/// Клон разделяет ту же цепочку. Порядок работы с блокировками:
+/// - каждый метод берёт блокировку сам и отпускает её до возврата, поэтому
+///   значения наружу отдаются копиями (`read_tip`, `snapshot`);
+/// - внутри замыканий `read` и `write`, а также в обработчиках
+///   `on_block_added` нельзя обращаться к той же `SharedBlockchain`:
+///   блокировка не реентерабельна, и повторный захват приведёт к взаимной
+///   блокировке;
+/// - долгую работу (например, проверку всей цепочки) лучше выполнять на копии
+///   из `snapshot`, чтобы не задерживать писателей.
+///
+/// Если поток запаниковал, удерживая блокировку на запись (например,
+/// внутри замыкания `write`), цепочка могла остаться частично изменённой.
+/// После этого все методы возвращают `ChainError::LockPoisoned`, пока
+/// `recover` не проверит цепочку и не снимет отравление.
+#[derive(Debug, Clone)]
+pub struct SharedBlockchain {
+    inner: Arc<RwLock<Blockchain>>,
+}
+
+impl SharedBlockchain {
+    pub fn new(chain: Blockchain) -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(chain)),
+        }
+    }
+
+    fn read_lock(&self) -> Result<RwLockReadGuard<'_, Blockchain>, ChainError> {
+        self.inner.read().map_err(|_| ChainError::LockPoisoned)
+    }
+
+    fn write_lock(&self) -> Result<RwLockWriteGuard<'_, Blockchain>, ChainError> {
+        self.inner.write().map_err(|_| ChainError::LockPoisoned)
+    }
+
+    /// Вызывает `f` под блокировкой на чтение.
+    pub fn read<R>(&self, f: impl FnOnce(&Blockchain) -> R) -> Result<R, ChainError> {
+        Ok(f(&*self.read_lock()?))
+    }
+
+    /// Вызывает `f` под блокировкой на запись.
+    pub fn write<R>(&self, f: impl FnOnce(&mut Blockchain) -> R) -> Result<R, ChainError> {
+        Ok(f(&mut *self.write_lock()?))
+    }
+
+    /// Копия последнего блока (`None` для пустой цепочки).
+    pub fn read_tip(&self) -> Result<Option<Block>, ChainError> {
+        Ok(self.read_lock()?.tip().cloned())
+    }
+
+    /// Добавляет блок под блокировкой на запись (см. `Blockchain::try_add_block`).
+    pub fn add_block(&self, transactions: Vec<Transaction>) -> Result<(), ChainError> {
+        self.write_lock()?.try_add_block(transactions)
+    }
+
+    /// Копия цепочки, снятая под блокировкой на чтение.
+    pub fn snapshot(&self) -> Result<Blockchain, ChainError> {
+        Ok(self.read_lock()?.clone())
+    }
+
+    /// Снимает отравление блокировки, если цепочка после паники прошла
+    /// `validate` (кеши перед проверкой сбрасываются). Иначе возвращается
+    /// ошибка проверки, а блокировка остаётся отравленной — тогда цепочку
+    /// можно заменить методом `reset`.
+    ///
+    /// Для неотравленной блокировки ничего не делает.
+    pub fn recover(&self) -> Result<(), ChainError> {
+        let Err(poisoned) = self.inner.write() else {
+            return Ok(());
+        };
+        let mut chain = poisoned.into_inner();
+        chain.invalidate_caches();
+        chain.validate()?;
+        drop(chain);
+        self.inner.clear_poison();
+        Ok(())
+    }
+
+    /// Заменяет цепочку на `chain` (например, на копию из `snapshot`)
+    /// и снимает отравление блокировки.
+    pub fn reset(&self, chain: Blockchain) {
+        *self.inner.write().unwrap_or_else(PoisonError::into_inner) = chain;
+        self.inner.clear_poison();
+    }
+}
+
+impl From<Blockchain> for SharedBlockchain {
+    fn from(chain: Blockchain) -> Self {
+        Self::new(chain)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_concurrent_readers_and_writer() {
+        let shared = SharedBlockchain::new(Blockchain::new());
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let shared = shared.clone();
+                thread::spawn(move || {
+                    let mut last = 0;
+                    for _ in 0..200 {
+                        let tip = shared.read_tip().unwrap().unwrap();
+                        assert!(tip.index >= last);
+                        last = tip.index;
+                        assert!(shared.read(|chain| chain.len()).unwrap() as u64 > last);
+                    }
+                })
+            })
+            .collect();
+        let writer = {
+            let shared = shared.clone();
+            thread::spawn(move || {
+                for i in 0..20u64 {
+                    let tx = Transaction::new([1; 32], [2; 32], 1).with_nonce(i);
+                    shared.add_block(vec![tx]).unwrap();
+                }
+            })
+        };
+        writer.join().unwrap();
+        for reader in readers {
+            reader.join().unwrap();
+        }
+
+        let chain = shared.snapshot().unwrap();
+        assert_eq!(chain.len(), 21);
+        assert!(chain.is_valid());
+        assert_eq!(shared.read_tip().unwrap().unwrap().index, 20);
+        assert!(matches!(
+            shared.add_block(vec![Transaction::new([1; 32], [2; 32], 1)]),
+            Err(ChainError::BadNonce { .. })
+        ));
+    }
+
+    #[test]
+    fn test_panic_in_write_poisons_until_recovered() {
+        let shared = SharedBlockchain::new(Blockchain::new());
+        shared
+            .add_block(vec![Transaction::new([1; 32], [2; 32], 1)])
+            .unwrap();
+        let backup = shared.snapshot().unwrap();
+
+        // Паника без изменений: после проверки цепочка снова доступна.
+        let panicker = shared.clone();
+        assert!(
+            thread::spawn(move || panicker.write(|_| panic!("запись прервана")))
+                .join()
+                .is_err()
+        );
+        assert!(matches!(shared.read_tip(), Err(ChainError::LockPoisoned)));
+        shared.recover().unwrap();
+        assert_eq!(shared.read_tip().unwrap().unwrap().index, 1);
+
+        let poisoner = shared.clone();
+        let result = thread::spawn(move || {
+            poisoner.write(|chain| {
+                chain.blocks[1].transactions[0].amount = 100;
+                panic!("запись прервана");
+            })
+        })
+        .join();
+        assert!(result.is_err());
+        assert!(matches!(shared.read_tip(), Err(ChainError::LockPoisoned)));
+        assert!(matches!(
+            shared.add_block(Vec::new()),
+            Err(ChainError::LockPoisoned)
+        ));
+
+        // Частично изменённая цепочка не проходит проверку.
+        assert!(shared.recover().is_err());
+        assert!(matches!(shared.snapshot(), Err(ChainError::LockPoisoned)));
+
+        shared.reset(backup.clone());
+        assert_eq!(shared.snapshot().unwrap(), backup);
+        shared
+            .add_block(vec![Transaction::new([1; 32], [2; 32], 1).with_nonce(1)])
+            .unwrap();
+    }
+}
@@ -0,0 +1,96 @@
+//! Детерминированная симуляция сети пиров (feature `simulate`).
+//!
+//! Все случайные решения — транзакции и голоса нечестных пиров — принимаются
+//! генератором, инициализированным `seed`, поэтому один и тот же `seed`
+//! всегда даёт одну и ту же цепочку.
+
+use crate::ChainBuilder;
+use crate::chain::{Blockchain, FixedPeerConsensus, Peer, PeerId};
+use log::debug;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// Симуляция сети из `peers` пиров, первые `dishonest` из которых голосуют
+/// случайно, а остальные всегда одобряют блок.
+///
+/// В каждом из `blocks` раундов предлагается блок со случайным переводом
+/// между адресами пиров; блок добавляется, если набрал строгое большинство
+/// голосов (см. [`FixedPeerConsensus::required_approvals`]). Цепочка
+/// строится через [`ChainBuilder`], поэтому её хеши зависят только от `seed`.
+pub fn simulate_network(peers: usize, dishonest: usize, blocks: usize, seed: u64) -> Blockchain {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let network = FixedPeerConsensus::new(
+        (1..=peers as PeerId)
+            .map(|id| {
+                if id as usize <= dishonest {
+                    Peer::dishonest(id)
+                } else {
+                    Peer::new(id)
+                }
+            })
+            .collect(),
+    );
+    let mut builder = ChainBuilder::new();
+    for round in 1..=blocks {
+        let from = rng.gen_range(1..=peers.max(1));
+        // Получатель — любой другой пир (в сети из одного пира — адрес вне сети)
+        let others = peers.max(2);
+        let to = (from + rng.gen_range(1..others) - 1) % others + 1;
+        let amount = rng.gen_range(1..=100);
+        let approvals = network
+            .peers
+            .iter()
+            .filter(|peer| peer.is_honest || rng.gen_bool(0.5))
+            .count();
+        debug!(
+            "Симуляция, раунд {}: {} из {} за, требуется {}",
+            round,
+            approvals,
+            network.peer_count(),
+            network.required_approvals()
+        );
+        if approvals >= network.required_approvals() {
+            builder = builder.transfer(address(from), address(to), amount);
+        }
+    }
+    builder.build()
+}
+
+/// Адрес пира с номером `n`: номер в little-endian в первых байтах.
+fn address(n: usize) -> [u8; 32] {
+    let mut addr = [0u8; 32];
+    addr[..8].copy_from_slice(&(n as u64).to_le_bytes());
+    addr
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tip_hash(chain: &Blockchain) -> [u8; 32] {
+        chain.tip().unwrap().hash
+    }
+
+    #[test]
+    fn test_same_seed_gives_same_chain() {
+        let first = simulate_network(5, 2, 20, 42);
+        let second = simulate_network(5, 2, 20, 42);
+        assert!(first.is_valid());
+        assert!(first.len() > 1);
+        assert_eq!(tip_hash(&first), tip_hash(&second));
+    }
+
+    #[test]
+    fn test_different_seeds_give_different_chains() {
+        let first = simulate_network(5, 2, 20, 1);
+        let second = simulate_network(5, 2, 20, 2);
+        assert_ne!(tip_hash(&first), tip_hash(&second));
+    }
+
+    #[test]
+    fn test_dishonest_majority_blocks_progress() {
+        let chain = simulate_network(3, 3, 50, 7);
+        assert!(chain.len() < 51);
+        assert_eq!(simulate_network(0, 0, 10, 7).len(), 1);
+    }
+}
@@ -0,0 +1,146 @@
+//! API для JavaScript (feature `wasm`): проверка и сериализация цепочки
+//! в браузере.
+//!
+//! Цепочка передаётся как байты bincode (`Uint8Array`) или как строка base64.
+//! Под `wasm32` время берётся из `Date.now()` через `SystemClock`, а файловые
+//! API не собираются.
+//!
+//! Тип `cdylib` не указан в `Cargo.toml`, чтобы не мешать сборке ядра без
+//! `std`, поэтому модуль для браузера собирается так:
+//!
+//! ```text
+//! cargo rustc --lib --release --target wasm32-unknown-unknown \
+//!     --no-default-features --features wasm --crate-type cdylib
+//! wasm-bindgen --target web --out-dir pkg \
+//!     target/wasm32-unknown-unknown/release/rustblockchain.wasm
+//! ```
+
+use crate::chain::{Blockchain, deserialize_blockchain, serialize_blockchain};
+use base64ct::{Base64, Encoding};
+use wasm_bindgen::prelude::*;
+
+/// Проверка цепочки, сериализованной в bincode.
+///
+/// Возвращает `false`, если байты не удаётся прочитать или цепочка
+/// некорректна.
+#[wasm_bindgen(js_name = validateChain)]
+pub fn validate_chain(bytes: &[u8]) -> bool {
+    deserialize_blockchain(bytes).is_ok_and(|chain| chain.is_valid())
+}
+
+/// То же, что `validate_chain`, для байтов, закодированных в base64.
+#[wasm_bindgen(js_name = validateChainBase64)]
+pub fn validate_chain_base64(data: &str) -> bool {
+    Base64::decode_vec(data).is_ok_and(|bytes| validate_chain(&bytes))
+}
+
+/// Цепочка, доступная из JavaScript как класс `Chain`.
+#[wasm_bindgen(js_name = Chain)]
+#[derive(Debug)]
+pub struct WasmChain {
+    inner: Blockchain,
+}
+
+#[wasm_bindgen(js_class = Chain)]
+impl WasmChain {
+    /// Новая цепочка из одного генезис-блока.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self {
+            inner: Blockchain::new(),
+        }
+    }
+
+    /// Чтение цепочки из байтов bincode без проверки (см. `is_valid`).
+    #[wasm_bindgen(js_name = fromBytes)]
+    pub fn from_bytes(bytes: &[u8]) -> Result<WasmChain, JsError> {
+        Ok(Self {
+            inner: deserialize_blockchain(bytes)?,
+        })
+    }
+
+    /// Чтение цепочки из строки base64 без проверки (см. `is_valid`).
+    #[wasm_bindgen(js_name = fromBase64)]
+    pub fn from_base64(data: &str) -> Result<WasmChain, JsError> {
+        let bytes =
+            Base64::decode_vec(data).map_err(|_| JsError::new("Некорректная строка base64"))?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// Полная проверка цепочки (см. `Blockchain::is_valid`).
+    #[wasm_bindgen(js_name = isValid)]
+    pub fn is_valid(&self) -> bool {
+        self.inner.is_valid()
+    }
+
+    /// Количество блоков.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    #[wasm_bindgen(js_name = isEmpty)]
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Сериализация цепочки в bincode.
+    #[wasm_bindgen(js_name = toBytes)]
+    pub fn to_bytes(&self) -> Result<Vec<u8>, JsError> {
+        Ok(serialize_blockchain(&self.inner)?)
+    }
+
+    /// Сериализация цепочки в bincode, закодированный в base64.
+    #[wasm_bindgen(js_name = toBase64)]
+    pub fn to_base64(&self) -> Result<String, JsError> {
+        Ok(Base64::encode_string(&self.to_bytes()?))
+    }
+}
+
+impl Default for WasmChain {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<Blockchain> for WasmChain {
+    fn from(inner: Blockchain) -> Self {
+        Self { inner }
+    }
+}
+
+impl From<WasmChain> for Blockchain {
+    fn from(chain: WasmChain) -> Self {
+        chain.inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ChainBuilder;
+
+    // Ошибки `JsError` вне `wasm32` создать нельзя, поэтому здесь проверяются
+    // только успешные вызовы и функции, возвращающие `bool`.
+
+    #[test]
+    fn test_base64_roundtrip() {
+        let chain = ChainBuilder::new()
+            .transfer([1; 32], [2; 32], 10)
+            .transfer([2; 32], [3; 32], 5)
+            .build();
+        let encoded = WasmChain::from(chain.clone()).to_base64().unwrap();
+        assert!(validate_chain_base64(&encoded));
+
+        let decoded = WasmChain::from_base64(&encoded).unwrap();
+        assert!(decoded.is_valid());
+        assert_eq!(decoded.len(), 3);
+        assert_eq!(Blockchain::from(decoded), chain);
+
+        let mut tampered = chain;
+        tampered.blocks[1].transactions[0].amount += 1;
+        let tampered = WasmChain::from(tampered).to_base64().unwrap();
+        assert!(!validate_chain_base64(&tampered));
+        assert!(!validate_chain_base64("не base64"));
+        assert!(!validate_chain(b"garbage"));
+    }
+}
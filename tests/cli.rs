@@ -0,0 +1,51 @@
+use assert_cmd::Command;
+
+fn cli() -> Command {
+    Command::cargo_bin("rustblockchain").unwrap()
+}
+
+#[test]
+fn test_new_then_validate() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("chain.bin");
+
+    cli().arg("new").arg(&path).assert().success();
+    assert!(path.exists());
+
+    let output = cli().arg("validate").arg(&path).output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Цепочка валидна (1 блоков)"));
+}
+
+#[test]
+fn test_add_then_stats() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("chain.bin");
+    let from = "01".repeat(32);
+    let to = "02".repeat(32);
+
+    cli().arg("new").arg(&path).assert().success();
+    cli()
+        .arg("add")
+        .arg(&path)
+        .arg(format!("{from}:{to}:10"))
+        .arg(format!("{from}:{to}:5"))
+        .assert()
+        .success();
+
+    let output = cli().arg("stats").arg(&path).output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Всего блоков: 2"));
+    assert!(stdout.contains("Общая сумма переводов: 15"));
+}
+
+#[test]
+fn test_validate_missing_file_fails() {
+    let dir = tempfile::tempdir().unwrap();
+    cli()
+        .arg("validate")
+        .arg(dir.path().join("missing.bin"))
+        .assert()
+        .failure();
+}
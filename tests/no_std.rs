@@ -0,0 +1,14 @@
+use std::process::Command;
+
+/// Ядро должно собираться без `std` (только с `alloc`) и без предупреждений.
+#[test]
+fn test_core_builds_without_default_features() {
+    let status = Command::new(env!("CARGO"))
+        .args(["build", "--lib", "--no-default-features", "--quiet"])
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .env("CARGO_TARGET_DIR", env!("CARGO_TARGET_TMPDIR"))
+        .env("RUSTFLAGS", "-D warnings")
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+
+use proptest::prelude::*;
+use rustblockchain::{
+    Blockchain, ChainBuilder, MAX_TRANSACTIONS_PER_BLOCK, Transaction, deserialize_blockchain,
+    serialize_blockchain,
+};
+
+/// Перевод без nonce: номера отправителя и получателя и сумма.
+///
+/// Отправитель берётся из `1..=8` (адрес `[0; 32]` занят coinbase), получатель
+/// задаётся ненулевым сдвигом относительно отправителя, поэтому перевод самому
+/// себе не генерируется и при сжатии.
+#[derive(Debug, Clone)]
+struct Transfer {
+    from: u8,
+    to: u8,
+    amount: u64,
+}
+
+fn transfer() -> impl Strategy<Value = Transfer> {
+    (1u8..=8, 1u8..8, 1u64..1_000).prop_map(|(from, shift, amount)| Transfer {
+        from,
+        to: (from - 1 + shift) % 8 + 1,
+        amount,
+    })
+}
+
+fn batches() -> impl Strategy<Value = Vec<Vec<Transfer>>> {
+    prop::collection::vec(
+        prop::collection::vec(transfer(), 0..=MAX_TRANSACTIONS_PER_BLOCK),
+        0..8,
+    )
+}
+
+/// Строит цепочку из пачек переводов, проставляя nonce по порядку.
+fn build_chain(batches: &[Vec<Transfer>]) -> Blockchain {
+    let mut nonces: HashMap<u8, u64> = HashMap::new();
+    let mut builder = ChainBuilder::new();
+    for batch in batches {
+        let transactions = batch
+            .iter()
+            .map(|t| {
+                let nonce = nonces.entry(t.from).or_insert(0);
+                let tx = Transaction::new([t.from; 32], [t.to; 32], t.amount).with_nonce(*nonce);
+                *nonce += 1;
+                tx
+            })
+            .collect();
+        builder = builder.block(transactions);
+    }
+    builder.build()
+}
+
+proptest! {
+    #[test]
+    fn prop_built_chain_is_valid(batches in batches()) {
+        let chain = build_chain(&batches);
+        prop_assert_eq!(chain.len(), batches.len() + 1);
+        prop_assert!(chain.validate().is_ok());
+    }
+
+    #[test]
+    fn prop_indices_are_contiguous(batches in batches()) {
+        let chain = build_chain(&batches);
+        for (position, block) in chain.iter().enumerate() {
+            prop_assert_eq!(block.index, position as u64);
+        }
+    }
+
+    #[test]
+    fn prop_timestamps_strictly_increase(batches in batches()) {
+        let chain = build_chain(&batches);
+        for pair in chain.blocks.windows(2) {
+            prop_assert!(pair[1].timestamp > pair[0].timestamp);
+        }
+    }
+
+    #[test]
+    fn prop_serialization_roundtrips(batches in batches()) {
+        let chain = build_chain(&batches);
+        let bytes = serialize_blockchain(&chain).unwrap();
+        let restored = deserialize_blockchain(&bytes).unwrap();
+        prop_assert_eq!(&restored, &chain);
+        prop_assert!(restored.is_valid());
+    }
+}